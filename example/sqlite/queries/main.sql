@@ -3,6 +3,62 @@ SELECT id AS user_id, email, created_at
 FROM users
 WHERE id = :id;
 
+-- get_user_by_email :one
+-- @param p1 as email
+SELECT id, email
+FROM users
+WHERE email = ?;
+
+-- find_user_by_email_pattern :one!
+SELECT id, email
+FROM users
+WHERE email LIKE :pattern;
+
+-- get_user_email :val
+SELECT email
+FROM users
+WHERE id = :id;
+
+-- list_user_emails :column
+SELECT email
+FROM users
+ORDER BY id;
+
+-- list_users_by_ids :many
+-- @slice ids
+SELECT id, email
+FROM users
+WHERE id IN (:ids)
+ORDER BY id;
+
+-- list_users_except_ids :many
+-- @slice ids
+SELECT id, email
+FROM users
+WHERE id NOT IN (:ids)
+ORDER BY id;
+
+-- list_users :many
+SELECT *
+FROM users
+ORDER BY id;
+
+-- iter_users_after :iter
+SELECT id, email
+FROM users
+WHERE id > :after_id
+ORDER BY id;
+
+-- list_users_page :many
+SELECT *
+FROM users
+ORDER BY id
+LIMIT :page_size OFFSET :page_offset;
+
+-- count_users :one
+SELECT COUNT(*) AS user_count
+FROM users;
+
 -- get_orders_with_items :many
 SELECT o.order_id, o.status, oi.product_id, oi.quantity
 FROM orders o
@@ -13,6 +69,15 @@ WHERE o.customer_id = :customer_id;
 INSERT INTO users (email, created_at)
 VALUES (:email, datetime('now'));
 
+-- create_user_returning :one
+INSERT INTO users (email, created_at)
+VALUES (:email, datetime('now'))
+RETURNING id, email, created_at;
+
+-- create_user_lastid :execlastid
+INSERT INTO users (email, created_at)
+VALUES (:email, datetime('now'));
+
 -- create_order_item :exec
 INSERT INTO order_items (order_id, product_id, quantity)
 VALUES (:order_id, :product_id, :quantity);
@@ -22,6 +87,11 @@ UPDATE users
 SET email = :email
 WHERE id = :id;
 
+-- replace_user_email :execrows
+UPDATE users
+SET email = :new_email
+WHERE email = :old_email;
+
 -- adjust_account_balance :exec
 UPDATE accounts
 SET balance = balance + :amount
@@ -83,6 +83,74 @@ def test_create_and_get_user(cursor: sqlite3.Cursor) -> None:
     print(f"  ✓ create_user / get_user_by_id: {result}")
 
 
+def test_create_user_returning(cursor: sqlite3.Cursor) -> None:
+    created = queries.create_user_returning(cursor, email="dana@example.com")
+    assert created is not None, "Expected RETURNING to produce a row"
+    assert created.email == "dana@example.com", (
+        f"Expected email 'dana@example.com', got '{created.email}'"
+    )
+
+    fetched = queries.get_user_by_id(cursor, id=created.id)
+    assert fetched is not None and fetched.user_id == created.id, (
+        f"Expected to fetch the returned id {created.id}"
+    )
+    print(f"  ✓ create_user_returning: {created}")
+
+
+def test_create_user_lastid(cursor: sqlite3.Cursor) -> None:
+    user_id = queries.create_user_lastid(cursor, email="erin@example.com")
+    assert user_id is not None, "Expected lastrowid after an INSERT"
+
+    fetched = queries.get_user_by_id(cursor, id=user_id)
+    assert fetched is not None and fetched.email == "erin@example.com", (
+        f"Expected the last inserted id {user_id} to be erin@example.com, got {fetched}"
+    )
+    print(f"  ✓ create_user_lastid: {user_id}")
+
+
+def test_replace_user_email(cursor: sqlite3.Cursor) -> None:
+    updated = queries.replace_user_email(
+        cursor, new_email="erin@example.org", old_email="erin@example.com"
+    )
+    assert updated == 1, f"Expected 1 row updated, got {updated}"
+
+    updated = queries.replace_user_email(
+        cursor, new_email="nobody@example.org", old_email="nobody@example.com"
+    )
+    assert updated == 0, f"Expected 0 rows updated for an unknown email, got {updated}"
+    print("  ✓ replace_user_email: rowcount reported")
+
+
+def test_get_user_by_email(cursor: sqlite3.Cursor) -> None:
+    result = queries.get_user_by_email(cursor, email="alice@example.com")
+    assert result is not None and result.email == "alice@example.com", (
+        f"Expected to find alice@example.com by email, got {result}"
+    )
+    print(f"  ✓ get_user_by_email: {result}")
+
+
+def test_find_user_by_email_pattern(cursor: sqlite3.Cursor) -> None:
+    result = queries.find_user_by_email_pattern(cursor, pattern="alice@%")
+    assert result.email == "alice@example.com", (
+        f"Expected exactly alice@example.com, got {result}"
+    )
+
+    try:
+        queries.find_user_by_email_pattern(cursor, pattern="nobody@%")
+    except queries.NoRowsError:
+        pass
+    else:
+        raise AssertionError("Expected NoRowsError for a pattern matching nothing")
+
+    try:
+        queries.find_user_by_email_pattern(cursor, pattern="%@example.%")
+    except queries.TooManyRowsError:
+        pass
+    else:
+        raise AssertionError("Expected TooManyRowsError for a pattern matching many")
+    print(f"  ✓ find_user_by_email_pattern: {result}")
+
+
 def test_get_user_by_id_missing(cursor: sqlite3.Cursor) -> None:
     result = queries.get_user_by_id(cursor, id=999999)
     assert result is None, "Expected None for a non-existent user id"
@@ -106,6 +174,93 @@ def test_update_user_email(cursor: sqlite3.Cursor) -> None:
     print(f"  ✓ update_user_email: {result}")
 
 
+def test_list_users(cursor: sqlite3.Cursor) -> None:
+    users = queries.list_users(cursor)
+    cursor.execute("SELECT id, email, created_at FROM users ORDER BY id")
+    expected = cursor.fetchall()
+    assert len(users) > 0, "Expected list_users to return the users created so far"
+    assert [(u.id, u.email, u.created_at) for u in users] == expected, (
+        "Expected SELECT * to expand to (id, email, created_at) in declaration order"
+    )
+    print(f"  ✓ list_users: {len(users)} row(s)")
+
+
+def test_iter_users_after(cursor: sqlite3.Cursor) -> None:
+    users = queries.iter_users_after(cursor, after_id=0, batch_size=1)
+    ids = [u.id for u in users]
+    expected = [
+        row[0] for row in cursor.execute("SELECT id FROM users ORDER BY id").fetchall()
+    ]
+    assert ids == expected, (
+        f"Expected iter_users_after to yield {expected} in batches, got {ids}"
+    )
+    print(f"  ✓ iter_users_after: {len(ids)} row(s)")
+
+
+def test_get_user_email(cursor: sqlite3.Cursor) -> None:
+    email = queries.get_user_email(cursor, id=1)
+    assert email == "alice@example.com", f"Expected alice@example.com, got {email!r}"
+
+    missing = queries.get_user_email(cursor, id=999999)
+    assert missing is None, f"Expected None for a non-existent user id, got {missing!r}"
+    print(f"  ✓ get_user_email: {email!r}")
+
+
+def test_list_user_emails(cursor: sqlite3.Cursor) -> None:
+    emails = queries.list_user_emails(cursor)
+    cursor.execute("SELECT email FROM users ORDER BY id")
+    expected = [row[0] for row in cursor.fetchall()]
+    assert emails == expected, f"Expected {expected}, got {emails}"
+    print(f"  ✓ list_user_emails: {emails}")
+
+
+def test_list_users_by_ids(cursor: sqlite3.Cursor) -> None:
+    users = queries.list_users_by_ids(cursor, ids=[1, 2, 999999])
+    assert [u.id for u in users] == [1, 2], (
+        f"Expected @slice ids to expand to users 1 and 2, got {users}"
+    )
+
+    none = queries.list_users_by_ids(cursor, ids=[])
+    assert none == [], f"Expected no users for an empty list, got {none}"
+    print(f"  ✓ list_users_by_ids: {len(users)} row(s)")
+
+
+def test_list_users_except_ids(cursor: sqlite3.Cursor) -> None:
+    cursor.execute("SELECT id FROM users ORDER BY id")
+    all_ids = [row[0] for row in cursor.fetchall()]
+
+    users = queries.list_users_except_ids(cursor, ids=[1])
+    assert [u.id for u in users] == [i for i in all_ids if i != 1], (
+        f"Expected NOT IN to exclude user 1, got {users}"
+    )
+
+    everyone = queries.list_users_except_ids(cursor, ids=[])
+    assert [u.id for u in everyone] == all_ids, (
+        f"Expected an empty NOT IN list to exclude nobody, got {everyone}"
+    )
+    print(f"  ✓ list_users_except_ids: {len(users)} row(s)")
+
+
+def test_list_users_page(cursor: sqlite3.Cursor) -> None:
+    page = queries.list_users_page(cursor, page_size=1, page_offset=1)
+    cursor.execute("SELECT id FROM users ORDER BY id LIMIT 1 OFFSET 1")
+    expected = [row[0] for row in cursor.fetchall()]
+    assert [u.id for u in page] == expected, (
+        f"Expected LIMIT/OFFSET parameters to select {expected}, got {page}"
+    )
+    print(f"  ✓ list_users_page: {page}")
+
+
+def test_count_users(cursor: sqlite3.Cursor) -> None:
+    result = queries.count_users(cursor)
+    cursor.execute("SELECT COUNT(*) FROM users")
+    expected = cursor.fetchone()[0]
+    assert result is not None and result.user_count == expected, (
+        f"Expected user_count {expected}, got {result}"
+    )
+    print(f"  ✓ count_users: {result}")
+
+
 def test_delete_user(cursor: sqlite3.Cursor) -> None:
     queries.create_user(cursor, email="charlie@example.com")
     cursor.execute("SELECT id FROM users WHERE email = 'charlie@example.com'")
@@ -127,8 +282,21 @@ def main() -> None:
 
     print("Running smoke tests …")
     test_create_and_get_user(cursor)
+    test_create_user_returning(cursor)
+    test_create_user_lastid(cursor)
+    test_replace_user_email(cursor)
+    test_get_user_by_email(cursor)
+    test_find_user_by_email_pattern(cursor)
     test_get_user_by_id_missing(cursor)
     test_update_user_email(cursor)
+    test_list_users(cursor)
+    test_iter_users_after(cursor)
+    test_get_user_email(cursor)
+    test_list_user_emails(cursor)
+    test_list_users_by_ids(cursor)
+    test_list_users_except_ids(cursor)
+    test_list_users_page(cursor)
+    test_count_users(cursor)
     test_delete_user(cursor)
 
     conn.close()
@@ -0,0 +1,763 @@
+use crate::codegen::naming::{is_identifier, to_camel_case, to_pascal_case};
+use crate::codegen::placeholders::{
+    empty_slice_sql, is_expanded_slice, rewrite_placeholders, slice_marker, PositionalStyle,
+};
+use crate::codegen::{Codegen, GeneratedFile};
+use crate::query::{classify_sql_type, QueryCardinality, QueryParseResult, SqlTypeClass};
+use crate::schema::SchemaParseResult;
+use std::path::PathBuf;
+
+const GO_KEYWORDS: &[&str] = &[
+    "break",
+    "case",
+    "chan",
+    "const",
+    "continue",
+    "default",
+    "defer",
+    "else",
+    "fallthrough",
+    "for",
+    "func",
+    "go",
+    "goto",
+    "if",
+    "import",
+    "interface",
+    "map",
+    "package",
+    "range",
+    "return",
+    "select",
+    "struct",
+    "switch",
+    "type",
+    "var",
+];
+
+/// Names the generated method bodies use for locals and imported packages;
+/// parameters must not shadow them.
+const GO_GENERATED_NAMES: &[&str] = &[
+    "ctx", "q", "row", "rows", "err", "items", "i", "sql", "errors", "context", "time", "iter",
+    "yield", "query", "args", "value", "strings", "result",
+];
+
+/// Sanitise a parameter name: append `_` if it is a Go keyword or collides
+/// with a name the generated code uses.
+fn sanitise_param_name(name: &str) -> String {
+    if GO_KEYWORDS.contains(&name) || GO_GENERATED_NAMES.contains(&name) {
+        format!("{}_", name)
+    } else {
+        name.to_string()
+    }
+}
+
+/// Map a SQL type to the Go type `database/sql` scans it into.
+pub fn sql_type_to_go(sql_type: &str) -> &'static str {
+    match classify_sql_type(sql_type) {
+        SqlTypeClass::Integer => "int64",
+        SqlTypeClass::Text | SqlTypeClass::Uuid | SqlTypeClass::Time | SqlTypeClass::Decimal => {
+            "string"
+        }
+        SqlTypeClass::Float => "float64",
+        SqlTypeClass::Boolean => "bool",
+        SqlTypeClass::Bytes | SqlTypeClass::Json => "[]byte",
+        SqlTypeClass::Date | SqlTypeClass::Timestamp => "time.Time",
+        SqlTypeClass::Unknown => "interface{}",
+    }
+}
+
+/// The Go type of a field or parameter. Nullable scalars use the matching
+/// `sql.Null*` wrapper; `[]byte` and `interface{}` can already hold nil.
+fn go_type(sql_type: Option<&str>, nullable: bool) -> String {
+    let go_type = sql_type.map(sql_type_to_go).unwrap_or("interface{}");
+    if !nullable {
+        return go_type.to_string();
+    }
+    match go_type {
+        "int64" => "sql.NullInt64",
+        "string" => "sql.NullString",
+        "float64" => "sql.NullFloat64",
+        "bool" => "sql.NullBool",
+        "time.Time" => "sql.NullTime",
+        other => other,
+    }
+    .to_string()
+}
+
+/// Quote `text` as a raw string literal, falling back to an interpreted
+/// string when the text contains a backtick.
+fn string_literal(text: &str) -> String {
+    if text.contains('`') {
+        format!("{:?}", text)
+    } else {
+        format!("`{}`", text)
+    }
+}
+
+/// Emit `lines` of `(name, type)` pairs with the type column aligned the way
+/// gofmt aligns struct fields.
+fn push_aligned_fields(out: &mut String, lines: &[(String, String)]) {
+    let width = lines.iter().map(|(name, _)| name.len()).max().unwrap_or(0);
+    for (name, ty) in lines {
+        out.push_str(&format!("\t{:width$} {}\n", name, ty, width = width));
+    }
+}
+
+/// The `db.go` file shared by every generated query file in the package.
+fn generate_db_file(package: &str) -> String {
+    let mut out = String::new();
+    out.push_str("// Code generated by icantbelieveitsnotsql. DO NOT EDIT.\n\n");
+    out.push_str(&format!("package {}\n\n", package));
+    out.push_str("import (\n\t\"context\"\n\t\"database/sql\"\n\t\"errors\"\n\t\"strings\"\n)\n\n");
+    out.push_str("type DBTX interface {\n");
+    out.push_str("\tExecContext(context.Context, string, ...interface{}) (sql.Result, error)\n");
+    out.push_str("\tQueryContext(context.Context, string, ...interface{}) (*sql.Rows, error)\n");
+    out.push_str("\tQueryRowContext(context.Context, string, ...interface{}) *sql.Row\n");
+    out.push_str("}\n\n");
+    out.push_str("func New(db DBTX) *Queries {\n\treturn &Queries{db: db}\n}\n\n");
+    out.push_str("type Queries struct {\n\tdb DBTX\n}\n\n");
+    out.push_str(
+        "// ErrTooManyRows is returned by `:one!` queries that match more than one row.\n",
+    );
+    out.push_str("var ErrTooManyRows = errors.New(\"query returned more than one row\")\n\n");
+    out.push_str("// sliceMarkers returns n comma-separated ? markers for a `@slice`\n");
+    out.push_str("// parameter, or empty when there are none.\n");
+    out.push_str("func sliceMarkers(n int, empty string) string {\n");
+    out.push_str("\tif n == 0 {\n\t\treturn empty\n\t}\n");
+    out.push_str("\treturn strings.Repeat(\"?, \", n-1) + \"?\"\n");
+    out.push_str("}\n");
+    out
+}
+
+pub struct GoCodegen {
+    pub package: String,
+    /// Bind-marker style of the target driver; `database/sql` drivers don't
+    /// accept `:name` placeholders.
+    pub style: PositionalStyle,
+    /// Whether the driver implements `sql.Result.LastInsertId`; PostgreSQL
+    /// drivers don't.
+    pub last_insert_id: bool,
+}
+
+impl Codegen for GoCodegen {
+    fn file_extension(&self) -> &'static str {
+        "go"
+    }
+
+    fn validate_query_name(&self, name: &str) -> Result<(), String> {
+        if !is_identifier(name) {
+            return Err("is not a valid Go identifier".to_string());
+        }
+        Ok(())
+    }
+
+    fn generate(
+        &self,
+        queries: &[QueryParseResult],
+        _schema: &SchemaParseResult,
+        source_filename: &str,
+        stem: &str,
+    ) -> Result<Vec<GeneratedFile>, std::io::Error> {
+        if stem == "db" {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!(
+                    "\"{}\" would be generated as db.go, which is the package's shared file; rename it",
+                    source_filename
+                ),
+            ));
+        }
+        Ok(vec![GeneratedFile {
+            path: PathBuf::from(format!("{}.{}", stem, self.file_extension())),
+            contents: generate_go_file(
+                queries,
+                source_filename,
+                &self.package,
+                self.style,
+                self.last_insert_id,
+            )?,
+        }])
+    }
+
+    /// The package's `db.go`.
+    fn shared_files(&self) -> Vec<GeneratedFile> {
+        vec![GeneratedFile {
+            path: PathBuf::from("db.go"),
+            contents: generate_db_file(&self.package),
+        }]
+    }
+}
+
+/// Generate the contents of a `.go` file from a list of parsed queries.
+pub fn generate_go_file(
+    queries: &[QueryParseResult],
+    source_filename: &str,
+    package: &str,
+    style: PositionalStyle,
+    last_insert_id: bool,
+) -> Result<String, std::io::Error> {
+    // Detect naming collisions
+    let mut method_names_seen: Vec<String> = Vec::new();
+    for query in queries {
+        let method_name = to_pascal_case(&query.annotation.name);
+        if !last_insert_id && matches!(query.annotation.cardinality, QueryCardinality::ExecLastId) {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!(
+                    "\"{}\" in \"{}\": :execlastid is not supported by PostgreSQL drivers, \
+                     use INSERT ... RETURNING with :one instead",
+                    query.annotation.name, source_filename
+                ),
+            ));
+        }
+        if style == PositionalStyle::Dollar {
+            if let Some(field) = query.input_fields.iter().find(|f| is_expanded_slice(f)) {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::InvalidInput,
+                    format!(
+                        "@slice parameter \"{}\" of \"{}\" in \"{}\" can't be expanded with $n placeholders",
+                        field.name, query.annotation.name, source_filename
+                    ),
+                ));
+            }
+        }
+        if method_names_seen.contains(&method_name) {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!(
+                    "duplicate method name \"{}\" in \"{}\"",
+                    method_name, source_filename
+                ),
+            ));
+        }
+        method_names_seen.push(method_name);
+    }
+
+    // Gather every type to determine imports
+    let mut all_types: Vec<String> = Vec::new();
+    for query in queries {
+        for f in &query.input_fields {
+            all_types.push(go_type(f.sql_type.as_deref(), f.nullable));
+        }
+        if !query.annotation.cardinality.is_exec() {
+            for f in &query.output_fields {
+                all_types.push(go_type(f.sql_type.as_deref(), f.nullable));
+            }
+        }
+    }
+    let has_one = queries.iter().any(|q| {
+        matches!(
+            q.annotation.cardinality,
+            QueryCardinality::One | QueryCardinality::Val
+        )
+    });
+
+    let has_exactly_one = queries
+        .iter()
+        .any(|q| matches!(q.annotation.cardinality, QueryCardinality::ExactlyOne));
+
+    let mut imports: Vec<&str> = vec!["context"];
+    if has_one || has_exactly_one || all_types.iter().any(|t| t.starts_with("sql.")) {
+        imports.push("database/sql");
+    }
+    if has_one {
+        imports.push("errors");
+    }
+    if queries
+        .iter()
+        .any(|q| matches!(q.annotation.cardinality, QueryCardinality::Iter))
+    {
+        imports.push("iter");
+    }
+    if queries
+        .iter()
+        .any(|q| q.input_fields.iter().any(is_expanded_slice))
+    {
+        imports.push("strings");
+    }
+    if all_types.iter().any(|t| t == "time.Time") {
+        imports.push("time");
+    }
+
+    let mut out = String::new();
+
+    // Header
+    out.push_str(&format!(
+        "// Code generated by icantbelieveitsnotsql. DO NOT EDIT.\n// Source: {}\n\n",
+        source_filename
+    ));
+    out.push_str(&format!("package {}\n\n", package));
+    out.push_str("import (\n");
+    for import in &imports {
+        out.push_str(&format!("\t\"{}\"\n", import));
+    }
+    out.push_str(")\n");
+
+    // One block per query
+    for query in queries {
+        let method_name = to_pascal_case(&query.annotation.name);
+        let const_name = format!("{}SQL", to_camel_case(&query.annotation.name));
+        let row_struct = format!("{}Row", method_name);
+
+        let rewritten = rewrite_placeholders(&query.statement, &query.input_fields, style);
+        out.push_str(&format!(
+            "\nconst {} = {}\n",
+            const_name,
+            string_literal(&rewritten.sql)
+        ));
+
+        let has_row_type = query.annotation.cardinality.has_row_type();
+
+        // Row struct (only for queries that return rows)
+        if has_row_type {
+            out.push('\n');
+            if query.output_fields.is_empty() {
+                out.push_str(&format!("type {} struct {{\n}}\n", row_struct));
+            } else {
+                out.push_str(&format!("type {} struct {{\n", row_struct));
+                let lines: Vec<(String, String)> = query
+                    .output_fields
+                    .iter()
+                    .map(|f| {
+                        (
+                            to_pascal_case(&f.name),
+                            go_type(f.sql_type.as_deref(), f.nullable),
+                        )
+                    })
+                    .collect();
+                push_aligned_fields(&mut out, &lines);
+                out.push_str("}\n");
+            }
+        }
+
+        let mut params = vec!["ctx context.Context".to_string()];
+        for f in &query.input_fields {
+            params.push(format!(
+                "{} {}",
+                sanitise_param_name(&to_camel_case(&f.name)),
+                if f.slice.is_some() {
+                    format!("[]{}", go_type(f.sql_type.as_deref(), f.nullable))
+                } else {
+                    go_type(f.sql_type.as_deref(), f.nullable)
+                }
+            ));
+        }
+
+        // Expanded `@slice` parameters splice one marker per value into the
+        // SQL and flatten their values into the arguments, so the statement
+        // and its arguments are built when the method runs.
+        let expanded: Vec<_> = query
+            .input_fields
+            .iter()
+            .filter(|f| is_expanded_slice(f))
+            .collect();
+        let mut body_prelude = String::new();
+        let call_args = if expanded.is_empty() {
+            let mut call_args = vec!["ctx".to_string(), const_name.clone()];
+            for name in &rewritten.args {
+                call_args.push(sanitise_param_name(&to_camel_case(name)));
+            }
+            call_args.join(", ")
+        } else {
+            let mut sql_ref = const_name.clone();
+            for field in &expanded {
+                body_prelude.push_str(&format!(
+                    "\tquery {} strings.ReplaceAll({}, \"{}\", sliceMarkers(len({}), \"{}\"))\n",
+                    if sql_ref == "query" { "=" } else { ":=" },
+                    sql_ref,
+                    slice_marker(field, "?"),
+                    sanitise_param_name(&to_camel_case(&field.name)),
+                    empty_slice_sql(field)
+                ));
+                sql_ref = "query".to_string();
+            }
+            body_prelude.push_str("\tvar args []interface{}\n");
+            for name in &rewritten.args {
+                let arg = sanitise_param_name(&to_camel_case(name));
+                if expanded.iter().any(|f| &f.name == name) {
+                    body_prelude.push_str(&format!(
+                        "\tfor _, value := range {} {{\n\t\targs = append(args, value)\n\t}}\n",
+                        arg
+                    ));
+                } else {
+                    body_prelude.push_str(&format!("\targs = append(args, {})\n", arg));
+                }
+            }
+            "ctx, query, args...".to_string()
+        };
+
+        // `:val` and `:column` scan their single column straight into the
+        // value instead of a row struct.
+        let (item_type, scan_args) = if query.annotation.cardinality.is_scalar() {
+            let field = &query.output_fields[0];
+            (
+                go_type(field.sql_type.as_deref(), field.nullable),
+                "&i".to_string(),
+            )
+        } else {
+            let scan_args: Vec<String> = query
+                .output_fields
+                .iter()
+                .map(|f| format!("&i.{}", to_pascal_case(&f.name)))
+                .collect();
+            (row_struct.clone(), scan_args.join(", "))
+        };
+
+        let return_type = match &query.annotation.cardinality {
+            QueryCardinality::One | QueryCardinality::Val => format!("(*{}, error)", item_type),
+            QueryCardinality::ExactlyOne => format!("({}, error)", row_struct),
+            QueryCardinality::Many | QueryCardinality::Column => {
+                format!("([]{}, error)", item_type)
+            }
+            QueryCardinality::Iter => format!("iter.Seq2[{}, error]", row_struct),
+            QueryCardinality::Exec => "error".to_string(),
+            QueryCardinality::ExecRows | QueryCardinality::ExecLastId => {
+                "(int64, error)".to_string()
+            }
+        };
+
+        out.push('\n');
+        out.push_str(&format!(
+            "func (q *Queries) {}({}) {} {{\n",
+            method_name,
+            params.join(", "),
+            return_type
+        ));
+        out.push_str(&body_prelude);
+
+        match &query.annotation.cardinality {
+            QueryCardinality::Exec => {
+                out.push_str(&format!("\t_, err := q.db.ExecContext({})\n", call_args));
+                out.push_str("\treturn err\n");
+            }
+            QueryCardinality::ExecRows | QueryCardinality::ExecLastId => {
+                out.push_str(&format!(
+                    "\tresult, err := q.db.ExecContext({})\n",
+                    call_args
+                ));
+                out.push_str("\tif err != nil {\n\t\treturn 0, err\n\t}\n");
+                if matches!(query.annotation.cardinality, QueryCardinality::ExecRows) {
+                    out.push_str("\treturn result.RowsAffected()\n");
+                } else {
+                    out.push_str("\treturn result.LastInsertId()\n");
+                }
+            }
+            QueryCardinality::One | QueryCardinality::Val => {
+                out.push_str(&format!("\trow := q.db.QueryRowContext({})\n", call_args));
+                out.push_str(&format!("\tvar i {}\n", item_type));
+                out.push_str(&format!(
+                    "\tif err := row.Scan({}); err != nil {{\n",
+                    scan_args
+                ));
+                out.push_str("\t\tif errors.Is(err, sql.ErrNoRows) {\n");
+                out.push_str("\t\t\treturn nil, nil\n");
+                out.push_str("\t\t}\n");
+                out.push_str("\t\treturn nil, err\n");
+                out.push_str("\t}\n");
+                out.push_str("\treturn &i, nil\n");
+            }
+            QueryCardinality::ExactlyOne => {
+                // QueryRowContext drops any rows after the first, so read the
+                // rows directly to notice a second one.
+                out.push_str(&format!("\tvar i {}\n", row_struct));
+                out.push_str(&format!(
+                    "\trows, err := q.db.QueryContext({})\n",
+                    call_args
+                ));
+                out.push_str("\tif err != nil {\n\t\treturn i, err\n\t}\n");
+                out.push_str("\tdefer rows.Close()\n");
+                out.push_str("\tif !rows.Next() {\n");
+                out.push_str(
+                    "\t\tif err := rows.Err(); err != nil {\n\t\t\treturn i, err\n\t\t}\n",
+                );
+                out.push_str("\t\treturn i, sql.ErrNoRows\n");
+                out.push_str("\t}\n");
+                out.push_str(&format!(
+                    "\tif err := rows.Scan({}); err != nil {{\n",
+                    scan_args
+                ));
+                out.push_str("\t\treturn i, err\n");
+                out.push_str("\t}\n");
+                out.push_str("\tif rows.Next() {\n");
+                out.push_str(&format!("\t\treturn {}{{}}, ErrTooManyRows\n", row_struct));
+                out.push_str("\t}\n");
+                out.push_str("\treturn i, rows.Err()\n");
+            }
+            QueryCardinality::Many | QueryCardinality::Column => {
+                out.push_str(&format!(
+                    "\trows, err := q.db.QueryContext({})\n",
+                    call_args
+                ));
+                out.push_str("\tif err != nil {\n\t\treturn nil, err\n\t}\n");
+                out.push_str("\tdefer rows.Close()\n");
+                out.push_str(&format!("\tvar items []{}\n", item_type));
+                out.push_str("\tfor rows.Next() {\n");
+                out.push_str(&format!("\t\tvar i {}\n", item_type));
+                out.push_str(&format!(
+                    "\t\tif err := rows.Scan({}); err != nil {{\n",
+                    scan_args
+                ));
+                out.push_str("\t\t\treturn nil, err\n");
+                out.push_str("\t\t}\n");
+                out.push_str("\t\titems = append(items, i)\n");
+                out.push_str("\t}\n");
+                out.push_str("\tif err := rows.Err(); err != nil {\n\t\treturn nil, err\n\t}\n");
+                out.push_str("\treturn items, nil\n");
+            }
+            QueryCardinality::Iter => {
+                // The query runs when iteration starts; rows are scanned one
+                // at a time and breaking out of the loop closes them.
+                out.push_str(&format!(
+                    "\treturn func(yield func({}, error) bool) {{\n",
+                    row_struct
+                ));
+                out.push_str(&format!(
+                    "\t\trows, err := q.db.QueryContext({})\n",
+                    call_args
+                ));
+                out.push_str("\t\tif err != nil {\n");
+                out.push_str(&format!("\t\t\tyield({}{{}}, err)\n", row_struct));
+                out.push_str("\t\t\treturn\n\t\t}\n");
+                out.push_str("\t\tdefer rows.Close()\n");
+                out.push_str("\t\tfor rows.Next() {\n");
+                out.push_str(&format!("\t\t\tvar i {}\n", row_struct));
+                out.push_str(&format!(
+                    "\t\t\tif err := rows.Scan({}); err != nil {{\n",
+                    scan_args
+                ));
+                out.push_str(&format!("\t\t\t\tyield({}{{}}, err)\n", row_struct));
+                out.push_str("\t\t\t\treturn\n\t\t\t}\n");
+                out.push_str("\t\t\tif !yield(i, nil) {\n\t\t\t\treturn\n\t\t\t}\n");
+                out.push_str("\t\t}\n");
+                out.push_str("\t\tif err := rows.Err(); err != nil {\n");
+                out.push_str(&format!("\t\t\tyield({}{{}}, err)\n", row_struct));
+                out.push_str("\t\t}\n");
+                out.push_str("\t}\n");
+            }
+        }
+        out.push_str("}\n");
+    }
+
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::codegen::test_support::parse_queries;
+    use std::path::Path;
+
+    #[test]
+    fn test_generate_go_file_cardinalities() {
+        let queries = parse_queries(
+            "-- get_user :one
+             SELECT id, email, bio FROM users WHERE id = :id;
+
+             -- get_user_by_email :one!
+             SELECT id, email FROM users WHERE email = :email;
+
+             -- list_users :many
+             SELECT id, email FROM users ORDER BY id;
+
+             -- get_email :val
+             SELECT email FROM users WHERE id = :id;
+
+             -- list_emails :column
+             SELECT email FROM users ORDER BY id;
+
+             -- iter_users_after :iter
+             SELECT id, email FROM users WHERE id > :after_id ORDER BY id;
+
+             -- delete_user :exec
+             DELETE FROM users WHERE id = :id;
+
+             -- clear_bio :execrows
+             UPDATE users SET bio = NULL WHERE bio = :bio;
+
+             -- create_user :execlastid
+             INSERT INTO users (email) VALUES (:email);",
+        );
+        let contents = generate_go_file(
+            &queries,
+            "users.sql",
+            "queries",
+            PositionalStyle::QuestionMark,
+            true,
+        )
+        .unwrap();
+
+        assert!(contents.contains(
+            "import (\n\t\"context\"\n\t\"database/sql\"\n\t\"errors\"\n\t\"iter\"\n)\n"
+        ));
+        for snippet in [
+            r#"
+const getUserSQL = `SELECT id, email, bio FROM users WHERE id = ?`
+
+type GetUserRow struct {
+	Id    int64
+	Email string
+	Bio   sql.NullString
+}
+"#,
+            r#"
+const getUserByEmailSQL = `SELECT id, email FROM users WHERE email = ?`
+
+type GetUserByEmailRow struct {
+	Id    int64
+	Email string
+}
+"#,
+            r#"
+const listUsersSQL = `SELECT id, email FROM users ORDER BY id`
+
+type ListUsersRow struct {
+	Id    int64
+	Email string
+}
+"#,
+            r#"
+const getEmailSQL = `SELECT email FROM users WHERE id = ?`
+
+func (q *Queries) GetEmail(ctx context.Context, id int64) (*string, error) {
+	row := q.db.QueryRowContext(ctx, getEmailSQL, id)
+	var i string
+	if err := row.Scan(&i); err != nil {
+		if errors.Is(err, sql.ErrNoRows) {
+			return nil, nil
+		}
+		return nil, err
+	}
+	return &i, nil
+}
+"#,
+            r#"
+const listEmailsSQL = `SELECT email FROM users ORDER BY id`
+
+func (q *Queries) ListEmails(ctx context.Context) ([]string, error) {
+	rows, err := q.db.QueryContext(ctx, listEmailsSQL)
+	if err != nil {
+		return nil, err
+	}
+	defer rows.Close()
+	var items []string
+	for rows.Next() {
+		var i string
+		if err := rows.Scan(&i); err != nil {
+			return nil, err
+		}
+		items = append(items, i)
+	}
+	if err := rows.Err(); err != nil {
+		return nil, err
+	}
+	return items, nil
+}
+"#,
+            r#"
+const iterUsersAfterSQL = `SELECT id, email FROM users WHERE id > ? ORDER BY id`
+
+type IterUsersAfterRow struct {
+	Id    int64
+	Email string
+}
+"#,
+            r#"
+const deleteUserSQL = `DELETE FROM users WHERE id = ?`
+
+func (q *Queries) DeleteUser(ctx context.Context, id int64) error {
+	_, err := q.db.ExecContext(ctx, deleteUserSQL, id)
+	return err
+}
+"#,
+            r#"
+const clearBioSQL = `UPDATE users SET bio = NULL WHERE bio = ?`
+
+func (q *Queries) ClearBio(ctx context.Context, bio sql.NullString) (int64, error) {
+	result, err := q.db.ExecContext(ctx, clearBioSQL, bio)
+	if err != nil {
+		return 0, err
+	}
+	return result.RowsAffected()
+}
+"#,
+            r#"
+const createUserSQL = `INSERT INTO users (email) VALUES (?)`
+
+func (q *Queries) CreateUser(ctx context.Context, email string) (int64, error) {
+	result, err := q.db.ExecContext(ctx, createUserSQL, email)
+	if err != nil {
+		return 0, err
+	}
+	return result.LastInsertId()
+}
+"#,
+        ] {
+            assert!(
+                contents.contains(snippet),
+                "missing:\n{}\nin:\n{}",
+                snippet,
+                contents
+            );
+        }
+    }
+
+    #[test]
+    fn test_generate_go_file_postgresql() {
+        let queries = parse_queries(
+            "-- get_email :val
+             SELECT email FROM users WHERE id = :id OR email = :email OR bio = :email;",
+        );
+        let contents = generate_go_file(
+            &queries,
+            "users.sql",
+            "queries",
+            PositionalStyle::Dollar,
+            false,
+        )
+        .unwrap();
+        assert!(contents.contains(
+            "const getEmailSQL = `SELECT email FROM users WHERE id = $1 OR email = $2 OR bio = $2`\n"
+        ));
+        assert!(contents.contains("q.db.QueryRowContext(ctx, getEmailSQL, id, email)"));
+
+        let lastid = parse_queries(
+            "-- create_user :execlastid
+             INSERT INTO users (email) VALUES (:email);",
+        );
+        assert!(generate_go_file(
+            &lastid,
+            "users.sql",
+            "queries",
+            PositionalStyle::Dollar,
+            false
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_go_codegen_shared_db_file() {
+        let backend = GoCodegen {
+            package: "queries".to_string(),
+            style: PositionalStyle::QuestionMark,
+            last_insert_id: true,
+        };
+        let schema = SchemaParseResult::default();
+        let queries = parse_queries(
+            "-- list_emails :column
+             SELECT email FROM users;",
+        );
+
+        let files = backend
+            .generate(&queries, &schema, "users.sql", "users")
+            .unwrap();
+        let paths: Vec<&Path> = files.iter().map(|f| f.path.as_path()).collect();
+        assert_eq!(paths, vec![Path::new("users.go")]);
+        assert!(backend.generate(&queries, &schema, "db.sql", "db").is_err());
+
+        let shared = backend.shared_files();
+        assert_eq!(shared.len(), 1);
+        assert_eq!(shared[0].path, PathBuf::from("db.go"));
+        assert!(shared[0].contents.contains("package queries\n"));
+    }
+}
@@ -0,0 +1,48 @@
+pub mod go;
+pub mod naming;
+pub mod placeholders;
+pub mod plugin;
+pub mod python;
+pub mod rust;
+#[cfg(test)]
+mod test_support;
+pub mod typescript;
+
+use crate::query::QueryParseResult;
+use crate::schema::SchemaParseResult;
+use std::path::PathBuf;
+
+/// A file produced by a codegen backend.
+pub struct GeneratedFile {
+    /// Path relative to the output directory.
+    pub path: PathBuf,
+    pub contents: String,
+}
+
+/// A target-language code generator.
+pub trait Codegen {
+    /// Extension (without the leading `.`) of the file generated for each
+    /// query file.
+    fn file_extension(&self) -> &'static str;
+
+    /// Check that a query annotation name can be turned into the function and
+    /// type names this backend emits. Returns a description of the problem
+    /// when it can't.
+    fn validate_query_name(&self, name: &str) -> Result<(), String>;
+
+    /// Generate the files for one query file. `stem` is the query file name
+    /// without its extension; `source_filename` is recorded in file headers.
+    fn generate(
+        &self,
+        queries: &[QueryParseResult],
+        schema: &SchemaParseResult,
+        source_filename: &str,
+        stem: &str,
+    ) -> Result<Vec<GeneratedFile>, std::io::Error>;
+
+    /// Files shared by every query file, written once after all of them
+    /// have been generated.
+    fn shared_files(&self) -> Vec<GeneratedFile> {
+        Vec::new()
+    }
+}
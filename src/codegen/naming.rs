@@ -0,0 +1,60 @@
+/// Convert a `snake_case` or `PascalCase` name to `PascalCase`.
+pub fn to_pascal_case(name: &str) -> String {
+    if name.contains('_') {
+        name.split('_')
+            .filter(|s| !s.is_empty())
+            .map(|s| {
+                let mut c = s.chars();
+                match c.next() {
+                    None => String::new(),
+                    Some(first) => first.to_uppercase().collect::<String>() + c.as_str(),
+                }
+            })
+            .collect()
+    } else {
+        let mut c = name.chars();
+        match c.next() {
+            None => String::new(),
+            Some(first) => first.to_uppercase().collect::<String>() + c.as_str(),
+        }
+    }
+}
+
+/// Convert a name to `SCREAMING_SNAKE_CASE`.
+pub fn to_screaming_snake(name: &str) -> String {
+    if name.contains('_') {
+        name.to_uppercase()
+    } else {
+        let mut result = String::new();
+        for (i, c) in name.chars().enumerate() {
+            if c.is_uppercase() && i > 0 {
+                result.push('_');
+            }
+            result.push(c.to_uppercase().next().unwrap());
+        }
+        result
+    }
+}
+
+/// Convert a `snake_case` or `PascalCase` name to `camelCase`.
+pub fn to_camel_case(name: &str) -> String {
+    let pascal = to_pascal_case(name);
+    let mut c = pascal.chars();
+    match c.next() {
+        None => String::new(),
+        Some(first) => first.to_lowercase().collect::<String>() + c.as_str(),
+    }
+}
+
+/// True if `name` is an identifier in the common sense shared by the target
+/// languages: a letter or `_` followed by letters, digits or `_`.
+pub fn is_identifier(name: &str) -> bool {
+    !name.is_empty()
+        && name.chars().enumerate().all(|(i, c)| {
+            if i == 0 {
+                c.is_alphabetic() || c == '_'
+            } else {
+                c.is_alphanumeric() || c == '_'
+            }
+        })
+}
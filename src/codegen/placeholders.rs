@@ -0,0 +1,293 @@
+use crate::config::ParamStyle;
+use crate::query::{placeholder_names, QueryInputField, SliceBinding};
+use sqlparser::ast::{visit_expressions_mut, Expr, Statement, Value};
+use sqlparser::tokenizer::Location;
+use std::ops::ControlFlow;
+
+/// A positional bind-marker style for drivers that don't accept `:name`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PositionalStyle {
+    /// `?` markers, bound in order of appearance (SQLite, MySQL).
+    QuestionMark,
+    /// `$1`, `$2`, ... markers, bound by number (PostgreSQL).
+    Dollar,
+}
+
+/// A statement whose placeholders have been rewritten to another style.
+pub struct RewrittenStatement {
+    /// The SQL text, without a trailing semicolon.
+    pub sql: String,
+    /// The names of the input fields to bind, in bind order. With `?` and
+    /// `%s` markers a parameter that is referenced twice appears twice.
+    pub args: Vec<String>,
+}
+
+/// The text that stands in for an expanded `@slice` parameter's bind
+/// markers. Generated code replaces it with one marker per value when the
+/// query runs.
+pub fn slice_marker(field: &QueryInputField, placeholder: &str) -> String {
+    format!("/*SLICE:{}*/{}", field.placeholder_name, placeholder)
+}
+
+/// True when `field` is a `@slice` parameter expanded at call time.
+pub fn is_expanded_slice(field: &QueryInputField) -> bool {
+    matches!(field.slice, Some(SliceBinding::Expanded { .. }))
+}
+
+/// What an expanded `@slice` parameter's [`slice_marker`] becomes when the
+/// list is empty: `IN (NULL)` matches no rows, and `NOT IN` lists, which
+/// [`nest_negated_slices`] has parenthesised, become `(x NOT IN (NULL) OR
+/// (1 = 1))` to match every row.
+pub fn empty_slice_sql(field: &QueryInputField) -> &'static str {
+    match field.slice {
+        Some(SliceBinding::Expanded { negated: true }) => "NULL) OR (1 = 1",
+        _ => "NULL",
+    }
+}
+
+/// Parenthesise `NOT IN (...)` lists whose item is a marked `@slice`
+/// parameter, so that the `OR` of [`empty_slice_sql`] stays inside them.
+fn nest_negated_slices(statement: &mut Statement) {
+    let _ = visit_expressions_mut(statement, |expr| {
+        if let Expr::InList {
+            list,
+            negated: true,
+            ..
+        } = expr
+        {
+            let is_slice = matches!(
+                list.as_slice(),
+                [Expr::Value(v)] if matches!(&v.value, Value::Placeholder(p) if p.starts_with("/*SLICE:"))
+            );
+            if is_slice {
+                *expr = Expr::Nested(Box::new(expr.clone()));
+            }
+        }
+        ControlFlow::<()>::Continue(())
+    });
+}
+
+/// Prefix the placeholders of expanded `@slice` parameters with their
+/// [`slice_marker`], leaving the SQL otherwise as written.
+pub fn mark_slices(statement: &Statement, input_fields: &[QueryInputField]) -> Statement {
+    let names = placeholder_names(statement);
+
+    let mut statement = statement.clone();
+    let _ = visit_expressions_mut(&mut statement, |expr| {
+        if let Expr::Value(v) = expr {
+            if let Value::Placeholder(placeholder) = &mut v.value {
+                // Placeholders without a source span (e.g. in a statement
+                // built by hand) aren't in `names`; use their raw text.
+                let name = names.get(&v.span.start).unwrap_or(placeholder);
+                if let Some(field) = input_fields
+                    .iter()
+                    .find(|f| &f.placeholder_name == name && is_expanded_slice(f))
+                {
+                    *placeholder = slice_marker(field, placeholder);
+                }
+            }
+        }
+        ControlFlow::<()>::Continue(())
+    });
+    nest_negated_slices(&mut statement);
+    statement
+}
+
+/// Rewrite every placeholder in `statement` to `style`.
+///
+/// `$n` numbers follow the order of `input_fields`, so each parameter is
+/// bound once however many times it is referenced. With `?` markers,
+/// expanded `@slice` parameters are marked for expansion at call time;
+/// `$n` markers can't be expanded that way.
+pub fn rewrite_placeholders(
+    statement: &Statement,
+    input_fields: &[QueryInputField],
+    style: PositionalStyle,
+) -> RewrittenStatement {
+    match style {
+        PositionalStyle::QuestionMark => {
+            rewrite_to_paramstyle(statement, input_fields, ParamStyle::Qmark)
+        }
+        PositionalStyle::Dollar => {
+            rewrite_to_paramstyle(statement, input_fields, ParamStyle::Dollar)
+        }
+    }
+}
+
+/// True when `style` binds arguments by marker position, so a parameter that
+/// is referenced twice is passed twice.
+pub fn binds_in_order(style: ParamStyle) -> bool {
+    matches!(style, ParamStyle::Qmark | ParamStyle::Format)
+}
+
+/// True when `style` binds arguments by name rather than position.
+pub fn binds_by_name(style: ParamStyle) -> bool {
+    matches!(style, ParamStyle::Named | ParamStyle::Pyformat)
+}
+
+/// Rewrite every placeholder in `statement` to a driver `paramstyle`.
+///
+/// Named styles use the input field's name, which `@param` may have changed.
+/// Numbered styles follow the order of `input_fields`. With the `format`
+/// styles any literal `%` in the SQL is doubled so the driver leaves it be.
+pub fn rewrite_to_paramstyle(
+    statement: &Statement,
+    input_fields: &[QueryInputField],
+    style: ParamStyle,
+) -> RewrittenStatement {
+    let names = placeholder_names(statement);
+
+    // `%` in our own markers is written as this stand-in until the literal
+    // ones have been escaped.
+    const PERCENT: char = '\u{1}';
+    let escapes_percent = matches!(style, ParamStyle::Format | ParamStyle::Pyformat);
+
+    let mut statement = statement.clone();
+    let mut bound: Vec<(Location, String)> = Vec::new();
+
+    let _ = visit_expressions_mut(&mut statement, |expr| {
+        if let Expr::Value(v) = expr {
+            if let Value::Placeholder(placeholder) = &mut v.value {
+                let name = names
+                    .get(&v.span.start)
+                    .cloned()
+                    .unwrap_or_else(|| placeholder.clone());
+                let position = input_fields.iter().position(|f| f.placeholder_name == name);
+                let name = position
+                    .map(|p| input_fields[p].name.clone())
+                    .unwrap_or(name);
+                let number = position.unwrap_or(input_fields.len()) + 1;
+                let marker = match style {
+                    ParamStyle::Qmark => "?".to_string(),
+                    ParamStyle::Numeric => format!(":{}", number),
+                    ParamStyle::Named => format!(":{}", name),
+                    ParamStyle::Format => format!("{}s", PERCENT),
+                    ParamStyle::Pyformat => format!("{}({})s", PERCENT, name),
+                    ParamStyle::Dollar => format!("${}", number),
+                };
+                *placeholder = match position {
+                    Some(position) if is_expanded_slice(&input_fields[position]) => {
+                        slice_marker(&input_fields[position], &marker)
+                    }
+                    _ => marker,
+                };
+                bound.push((v.span.start, name));
+            }
+        }
+        ControlFlow::<()>::Continue(())
+    });
+    nest_negated_slices(&mut statement);
+
+    let args = if binds_in_order(style) {
+        bound.sort_by_key(|(location, _)| *location);
+        bound.into_iter().map(|(_, name)| name).collect()
+    } else {
+        input_fields.iter().map(|f| f.name.clone()).collect()
+    };
+
+    let mut sql = statement
+        .to_string()
+        .trim_end_matches(';')
+        .trim()
+        .to_string();
+    if escapes_percent {
+        sql = sql.replace('%', "%%").replace(PERCENT, "%");
+    }
+
+    RewrittenStatement { sql, args }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::query::build_input_fields;
+    use crate::schema::SchemaParseResult;
+    use sqlparser::dialect::SQLiteDialect;
+    use sqlparser::parser::Parser as SQLParser;
+
+    #[test]
+    fn test_rewrite_placeholders_repeated_named() {
+        let sql = "SELECT id FROM users WHERE email = :email OR backup_email = :email AND id > :id";
+        let ast = SQLParser::parse_sql(&SQLiteDialect {}, sql).unwrap();
+        let schema = SchemaParseResult { tables: vec![] };
+        let raw = vec![
+            ":email".to_string(),
+            ":email".to_string(),
+            ":id".to_string(),
+        ];
+        let input_fields = build_input_fields(&raw, &[], &schema);
+
+        let dollar = rewrite_placeholders(&ast[0], &input_fields, PositionalStyle::Dollar);
+        assert_eq!(
+            dollar.sql,
+            "SELECT id FROM users WHERE email = $1 OR backup_email = $1 AND id > $2"
+        );
+        assert_eq!(dollar.args, vec!["email", "id"]);
+
+        let qmark = rewrite_placeholders(&ast[0], &input_fields, PositionalStyle::QuestionMark);
+        assert_eq!(
+            qmark.sql,
+            "SELECT id FROM users WHERE email = ? OR backup_email = ? AND id > ?"
+        );
+        assert_eq!(qmark.args, vec!["email", "email", "id"]);
+    }
+
+    #[test]
+    fn test_rewrite_to_paramstyle() {
+        let sql =
+            "SELECT id FROM users WHERE email LIKE '%@' || :domain AND id > :id AND id <> :id";
+        let ast = SQLParser::parse_sql(&SQLiteDialect {}, sql).unwrap();
+        let schema = SchemaParseResult { tables: vec![] };
+        let raw = vec![":domain".to_string(), ":id".to_string(), ":id".to_string()];
+        let input_fields = build_input_fields(&raw, &[], &schema);
+
+        let rewrite = |style| rewrite_to_paramstyle(&ast[0], &input_fields, style);
+
+        let numeric = rewrite(ParamStyle::Numeric);
+        assert_eq!(
+            numeric.sql,
+            "SELECT id FROM users WHERE email LIKE '%@' || :1 AND id > :2 AND id <> :2"
+        );
+        assert_eq!(numeric.args, vec!["domain", "id"]);
+
+        let named = rewrite(ParamStyle::Named);
+        assert_eq!(
+            named.sql,
+            "SELECT id FROM users WHERE email LIKE '%@' || :domain AND id > :id AND id <> :id"
+        );
+        assert_eq!(named.args, vec!["domain", "id"]);
+
+        let format = rewrite(ParamStyle::Format);
+        assert_eq!(
+            format.sql,
+            "SELECT id FROM users WHERE email LIKE '%%@' || %s AND id > %s AND id <> %s"
+        );
+        assert_eq!(format.args, vec!["domain", "id", "id"]);
+
+        let pyformat = rewrite(ParamStyle::Pyformat);
+        assert_eq!(
+            pyformat.sql,
+            "SELECT id FROM users WHERE email LIKE '%%@' || %(domain)s AND id > %(id)s AND id <> %(id)s"
+        );
+        assert_eq!(pyformat.args, vec!["domain", "id"]);
+    }
+
+    #[test]
+    fn test_mark_slices_not_in() {
+        let sql = "SELECT id FROM users WHERE id IN (:ids) AND email NOT IN (:skip)";
+        let ast = SQLParser::parse_sql(&SQLiteDialect {}, sql).unwrap();
+        let schema = SchemaParseResult { tables: vec![] };
+        let raw = vec![":ids".to_string(), ":skip".to_string()];
+        let mut input_fields = build_input_fields(&raw, &[], &schema);
+        input_fields[0].slice = Some(SliceBinding::Expanded { negated: false });
+        input_fields[1].slice = Some(SliceBinding::Expanded { negated: true });
+
+        assert_eq!(
+            mark_slices(&ast[0], &input_fields).to_string(),
+            "SELECT id FROM users WHERE id IN (/*SLICE:ids*/:ids) \
+             AND (email NOT IN (/*SLICE:skip*/:skip))"
+        );
+        assert_eq!(empty_slice_sql(&input_fields[0]), "NULL");
+        assert_eq!(empty_slice_sql(&input_fields[1]), "NULL) OR (1 = 1");
+    }
+}
@@ -0,0 +1,573 @@
+use crate::codegen::naming::is_identifier;
+use crate::codegen::{Codegen, GeneratedFile};
+use crate::config::ParamStyle;
+use crate::query::{PlaceholderKind, QueryParseResult, SliceBinding};
+use crate::schema::{Constraint, SchemaParseResult, TableKind};
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use std::path::{Component, Path, PathBuf};
+use std::process::{Command, Stdio};
+
+/// Version of the JSON protocol spoken with plugins. Bumped on breaking
+/// changes to `PluginRequest`.
+const PROTOCOL_VERSION: u32 = 1;
+
+// ---------------------------------------------------------------------------
+// Wire types
+// ---------------------------------------------------------------------------
+
+/// The JSON document written to the plugin's stdin, once per query file.
+#[derive(Serialize)]
+struct PluginRequest<'a> {
+    version: u32,
+    dialect: &'a str,
+    /// The configured `paramstyle`, or null when unset.
+    paramstyle: Option<&'static str>,
+    source_filename: &'a str,
+    stem: &'a str,
+    schema: PluginSchema<'a>,
+    queries: Vec<PluginQuery<'a>>,
+}
+
+#[derive(Serialize)]
+struct PluginSchema<'a> {
+    /// Tables in declaration order.
+    tables: Vec<PluginTable<'a>>,
+}
+
+#[derive(Serialize)]
+struct PluginTable<'a> {
+    name: &'a str,
+    /// `table` or `view`
+    kind: &'static str,
+    schema: Option<&'a str>,
+    /// Columns in declaration order.
+    columns: Vec<PluginColumn<'a>>,
+    primary_key: &'a [String],
+    constraints: Vec<PluginConstraint<'a>>,
+}
+
+#[derive(Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+enum PluginConstraint<'a> {
+    Unique {
+        name: Option<&'a str>,
+        columns: &'a [String],
+    },
+    ForeignKey {
+        name: Option<&'a str>,
+        columns: &'a [String],
+        foreign_table: &'a str,
+        referred_columns: &'a [String],
+    },
+    Check {
+        name: Option<&'a str>,
+        expr: &'a str,
+    },
+}
+
+#[derive(Serialize)]
+struct PluginColumn<'a> {
+    name: &'a str,
+    data_type: &'a str,
+    nullable: bool,
+}
+
+#[derive(Serialize)]
+struct PluginQuery<'a> {
+    name: &'a str,
+    /// The annotation cardinality without its colon: `one`, `one!`, `many`,
+    /// `val`, `column`, `iter`, `exec`, `execrows` or `execlastid`
+    cardinality: String,
+    sql: String,
+    input_fields: Vec<PluginInputField<'a>>,
+    output_fields: Vec<PluginOutputField<'a>>,
+}
+
+#[derive(Serialize)]
+struct PluginInputField<'a> {
+    name: &'a str,
+    sql_type: Option<&'a str>,
+    python_type: &'a str,
+    nullable: bool,
+    /// `named`, `dollar` or `question_mark`
+    placeholder_kind: &'static str,
+    /// The name the placeholder binds under, which differs from `name` when
+    /// an `@param` directive renames the parameter.
+    placeholder_name: &'a str,
+    /// For `@slice` parameters: `expanded` when the placeholder is to be
+    /// replaced with one marker per value at call time (`expanded-not-in`
+    /// in a `NOT IN (...)` list, where an empty list must match every row),
+    /// `array` when the list is bound as a single array.
+    slice: Option<&'static str>,
+}
+
+#[derive(Serialize)]
+struct PluginOutputField<'a> {
+    name: &'a str,
+    sql_type: Option<&'a str>,
+    python_type: &'a str,
+    nullable: bool,
+    source: PluginFieldSource<'a>,
+}
+
+#[derive(Serialize)]
+struct PluginFieldSource<'a> {
+    database: Option<&'a str>,
+    schema: Option<&'a str>,
+    table: Option<&'a str>,
+    field: &'a str,
+}
+
+/// One element of the JSON list the plugin writes to its stdout.
+#[derive(Deserialize)]
+struct PluginFile {
+    path: PathBuf,
+    contents: String,
+}
+
+fn placeholder_kind_name(kind: &PlaceholderKind) -> &'static str {
+    match kind {
+        PlaceholderKind::Named => "named",
+        PlaceholderKind::Dollar => "dollar",
+        PlaceholderKind::QuestionMark => "question_mark",
+    }
+}
+
+fn plugin_constraint(constraint: &Constraint) -> PluginConstraint<'_> {
+    match constraint {
+        Constraint::Unique { name, columns } => PluginConstraint::Unique {
+            name: name.as_deref(),
+            columns,
+        },
+        Constraint::ForeignKey {
+            name,
+            columns,
+            foreign_table,
+            referred_columns,
+        } => PluginConstraint::ForeignKey {
+            name: name.as_deref(),
+            columns,
+            foreign_table,
+            referred_columns,
+        },
+        Constraint::Check { name, expr } => PluginConstraint::Check {
+            name: name.as_deref(),
+            expr,
+        },
+    }
+}
+
+fn build_request<'a>(
+    dialect: &'a str,
+    paramstyle: Option<ParamStyle>,
+    queries: &'a [QueryParseResult],
+    schema: &'a SchemaParseResult,
+    source_filename: &'a str,
+    stem: &'a str,
+) -> PluginRequest<'a> {
+    let tables: Vec<PluginTable> = schema
+        .tables
+        .iter()
+        .map(|table| PluginTable {
+            name: &table.name,
+            kind: match table.kind {
+                TableKind::Table => "table",
+                TableKind::View => "view",
+                TableKind::Cte => "cte",
+            },
+            schema: table.schema.as_deref(),
+            columns: table
+                .columns
+                .iter()
+                .map(|column| PluginColumn {
+                    name: &column.name,
+                    data_type: &column.data_type,
+                    nullable: column.nullable,
+                })
+                .collect(),
+            primary_key: &table.primary_key,
+            constraints: table.constraints.iter().map(plugin_constraint).collect(),
+        })
+        .collect();
+
+    let queries = queries
+        .iter()
+        .map(|query| PluginQuery {
+            name: &query.annotation.name,
+            cardinality: query
+                .annotation
+                .cardinality
+                .to_string()
+                .trim_start_matches(':')
+                .to_string(),
+            sql: query
+                .statement
+                .to_string()
+                .trim_end_matches(';')
+                .trim()
+                .to_string(),
+            input_fields: query
+                .input_fields
+                .iter()
+                .map(|f| PluginInputField {
+                    name: &f.name,
+                    sql_type: f.sql_type.as_deref(),
+                    python_type: &f.data_type,
+                    nullable: f.nullable,
+                    placeholder_kind: placeholder_kind_name(&f.placeholder_kind),
+                    placeholder_name: &f.placeholder_name,
+                    slice: f.slice.map(|slice| match slice {
+                        SliceBinding::Expanded { negated: false } => "expanded",
+                        SliceBinding::Expanded { negated: true } => "expanded-not-in",
+                        SliceBinding::Array => "array",
+                    }),
+                })
+                .collect(),
+            output_fields: query
+                .output_fields
+                .iter()
+                .map(|f| PluginOutputField {
+                    name: &f.name,
+                    sql_type: f.sql_type.as_deref(),
+                    python_type: &f.python_type,
+                    nullable: f.nullable,
+                    source: PluginFieldSource {
+                        database: f.source.database.as_deref(),
+                        schema: f.source.schema.as_deref(),
+                        table: f.source.table.as_deref(),
+                        field: &f.source.field,
+                    },
+                })
+                .collect(),
+        })
+        .collect();
+
+    PluginRequest {
+        version: PROTOCOL_VERSION,
+        dialect,
+        paramstyle: paramstyle.map(|style| style.as_str()),
+        source_filename,
+        stem,
+        schema: PluginSchema { tables },
+        queries,
+    }
+}
+
+/// Reject paths that would let a plugin write outside the output directory.
+fn is_contained_path(path: &Path) -> bool {
+    path.components()
+        .all(|component| matches!(component, Component::Normal(_) | Component::CurDir))
+}
+
+// ---------------------------------------------------------------------------
+// Backend
+// ---------------------------------------------------------------------------
+
+/// Delegates code generation to an external executable.
+///
+/// For each query file the plugin is run once with a `PluginRequest` JSON
+/// document on stdin and must print a JSON list of `{"path", "contents"}`
+/// objects to stdout. Paths are relative to the output directory. Anything
+/// the plugin writes to stderr is passed through.
+pub struct PluginCodegen {
+    pub command: PathBuf,
+    pub dialect: String,
+    /// Passed through to the plugin, which decides how to honour it.
+    pub paramstyle: Option<ParamStyle>,
+}
+
+impl Codegen for PluginCodegen {
+    /// Plugins name their own files.
+    fn file_extension(&self) -> &'static str {
+        ""
+    }
+
+    fn validate_query_name(&self, name: &str) -> Result<(), String> {
+        if !is_identifier(name) {
+            return Err("is not a valid identifier".to_string());
+        }
+        Ok(())
+    }
+
+    fn generate(
+        &self,
+        queries: &[QueryParseResult],
+        schema: &SchemaParseResult,
+        source_filename: &str,
+        stem: &str,
+    ) -> Result<Vec<GeneratedFile>, std::io::Error> {
+        let request = build_request(
+            &self.dialect,
+            self.paramstyle,
+            queries,
+            schema,
+            source_filename,
+            stem,
+        );
+        let input = serde_json::to_vec(&request)?;
+
+        let mut child = Command::new(&self.command)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::inherit())
+            .spawn()
+            .map_err(|err| {
+                std::io::Error::new(
+                    err.kind(),
+                    format!(
+                        "failed to run plugin \"{}\": {}",
+                        self.command.display(),
+                        err
+                    ),
+                )
+            })?;
+
+        // Write the request from another thread while reading the output
+        // here; a plugin that writes before it has read everything would
+        // otherwise fill its stdout pipe and wait on us forever.
+        let stdin = child.stdin.take();
+        let writer = std::thread::spawn(move || match stdin {
+            Some(mut stdin) => stdin.write_all(&input),
+            None => Ok(()),
+        });
+        let output = child.wait_with_output()?;
+        let written = writer.join().expect("plugin request writer panicked");
+
+        if !output.status.success() {
+            return Err(std::io::Error::other(format!(
+                "plugin \"{}\" exited with {}",
+                self.command.display(),
+                output.status
+            )));
+        }
+        written.map_err(|err| {
+            let reason = if err.kind() == std::io::ErrorKind::BrokenPipe {
+                "it exited before reading the whole request".to_string()
+            } else {
+                err.to_string()
+            };
+            std::io::Error::new(
+                err.kind(),
+                format!(
+                    "failed to send the request to plugin \"{}\": {}",
+                    self.command.display(),
+                    reason
+                ),
+            )
+        })?;
+
+        let files: Vec<PluginFile> = serde_json::from_slice(&output.stdout).map_err(|err| {
+            std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!(
+                    "plugin \"{}\" returned invalid output: {}",
+                    self.command.display(),
+                    err
+                ),
+            )
+        })?;
+
+        files
+            .into_iter()
+            .map(|file| {
+                if !is_contained_path(&file.path) {
+                    return Err(std::io::Error::new(
+                        std::io::ErrorKind::InvalidData,
+                        format!(
+                            "plugin \"{}\" returned a path outside the output directory: {}",
+                            self.command.display(),
+                            file.path.display()
+                        ),
+                    ));
+                }
+                Ok(GeneratedFile {
+                    path: file.path,
+                    contents: file.contents,
+                })
+            })
+            .collect()
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use crate::query::{process_sql_statement, QueryAnnotation, QueryCardinality};
+    use sqlparser::dialect::SQLiteDialect;
+    use sqlparser::parser::Parser as SQLParser;
+    use std::fs;
+    use std::os::unix::fs::PermissionsExt;
+
+    /// Write an executable shell script to a fresh temp directory.
+    fn write_plugin(name: &str, body: &str) -> (PathBuf, PathBuf) {
+        let dir = std::env::temp_dir().join(format!(
+            "icantbelieveitsnotsql-plugin-{}-{}",
+            name,
+            std::process::id()
+        ));
+        fs::create_dir_all(&dir).unwrap();
+        let script = dir.join("plugin.sh");
+        fs::write(&script, format!("#!/bin/sh\n{}\n", body)).unwrap();
+        fs::set_permissions(&script, fs::Permissions::from_mode(0o755)).unwrap();
+        (dir, script)
+    }
+
+    fn parse_queries() -> (Vec<QueryParseResult>, SchemaParseResult) {
+        parse_queries_with_schema("CREATE TABLE users (id INTEGER PRIMARY KEY, email TEXT);")
+    }
+
+    fn parse_queries_with_schema(schema_sql: &str) -> (Vec<QueryParseResult>, SchemaParseResult) {
+        let mut schema = SchemaParseResult::default();
+        schema.apply_sql(schema_sql, &SQLiteDialect {}).unwrap();
+        let ast = SQLParser::parse_sql(
+            &SQLiteDialect {},
+            "SELECT id, email FROM users WHERE id = :id",
+        )
+        .unwrap();
+        let annotation = QueryAnnotation {
+            name: "get_user".to_string(),
+            cardinality: QueryCardinality::One,
+            params: Vec::new(),
+        };
+        let query = process_sql_statement(&ast[0], annotation, &schema).unwrap();
+        (vec![query], schema)
+    }
+
+    #[test]
+    fn test_plugin_round_trip() {
+        let (dir, script) = write_plugin(
+            "round-trip",
+            "cat > \"$(dirname \"$0\")/request.json\"\n\
+             echo '[{\"path\": \"out/users.txt\", \"contents\": \"hello\"}]'",
+        );
+        let (queries, schema) = parse_queries();
+        let backend = PluginCodegen {
+            command: script,
+            dialect: "sqlite".to_string(),
+            paramstyle: Some(ParamStyle::Qmark),
+        };
+
+        let files = backend
+            .generate(&queries, &schema, "users.sql", "users")
+            .unwrap();
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].path, PathBuf::from("out/users.txt"));
+        assert_eq!(files[0].contents, "hello");
+
+        let request: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(dir.join("request.json")).unwrap()).unwrap();
+        assert_eq!(request["version"], 1);
+        assert_eq!(request["stem"], "users");
+        assert_eq!(request["paramstyle"], "qmark");
+        assert_eq!(request["queries"][0]["name"], "get_user");
+        assert_eq!(request["queries"][0]["cardinality"], "one");
+        assert_eq!(request["queries"][0]["input_fields"][0]["name"], "id");
+        assert_eq!(
+            request["queries"][0]["input_fields"][0]["placeholder_kind"],
+            "named"
+        );
+        assert_eq!(
+            request["queries"][0]["output_fields"][1]["sql_type"],
+            "TEXT"
+        );
+        assert_eq!(request["queries"][0]["output_fields"][1]["nullable"], true);
+        assert_eq!(request["schema"]["tables"][0]["name"], "users");
+        assert_eq!(request["schema"]["tables"][0]["primary_key"][0], "id");
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_plugin_rejects_escaping_paths() {
+        let (dir, script) = write_plugin(
+            "escape",
+            "cat > /dev/null\necho '[{\"path\": \"../evil.txt\", \"contents\": \"\"}]'",
+        );
+        let (queries, schema) = parse_queries();
+        let backend = PluginCodegen {
+            command: script,
+            dialect: "sqlite".to_string(),
+            paramstyle: None,
+        };
+
+        assert!(backend
+            .generate(&queries, &schema, "users.sql", "users")
+            .is_err());
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_plugin_failure_exit_status() {
+        let (dir, script) = write_plugin("failure", "cat > /dev/null\nexit 3");
+        let (queries, schema) = parse_queries();
+        let backend = PluginCodegen {
+            command: script,
+            dialect: "sqlite".to_string(),
+            paramstyle: None,
+        };
+
+        assert!(backend
+            .generate(&queries, &schema, "users.sql", "users")
+            .is_err());
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    /// A schema large enough that its request doesn't fit in a pipe buffer.
+    fn large_schema_sql() -> String {
+        let mut sql = "CREATE TABLE users (id INTEGER PRIMARY KEY, email TEXT);".to_string();
+        for i in 0..2000 {
+            sql.push_str(&format!(
+                "CREATE TABLE padding_table_{} (id INTEGER PRIMARY KEY, description TEXT);",
+                i
+            ));
+        }
+        sql
+    }
+
+    #[test]
+    fn test_plugin_writes_before_reading() {
+        let (dir, script) = write_plugin(
+            "streaming",
+            "printf '[{\"path\": \"big.txt\", \"contents\": \"'\n\
+             head -c 200000 /dev/zero | tr '\\0' a\n\
+             cat > /dev/null\n\
+             printf '\"}]'",
+        );
+        let (queries, schema) = parse_queries_with_schema(&large_schema_sql());
+        let backend = PluginCodegen {
+            command: script,
+            dialect: "sqlite".to_string(),
+            paramstyle: None,
+        };
+
+        let files = backend
+            .generate(&queries, &schema, "users.sql", "users")
+            .unwrap();
+        assert_eq!(files[0].contents.len(), 200000);
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_plugin_exits_before_reading() {
+        let (dir, script) = write_plugin("early-exit", "echo '[]'");
+        let (queries, schema) = parse_queries_with_schema(&large_schema_sql());
+        let backend = PluginCodegen {
+            command: script,
+            dialect: "sqlite".to_string(),
+            paramstyle: None,
+        };
+
+        match backend.generate(&queries, &schema, "users.sql", "users") {
+            Err(err) => assert!(err
+                .to_string()
+                .contains("exited before reading the whole request")),
+            Ok(_) => panic!("expected the plugin to fail"),
+        }
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
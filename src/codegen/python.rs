@@ -1,8 +1,13 @@
-use crate::query::{
-    PlaceholderKind, QueryCardinality, QueryInputField, QueryOutputField, QueryParseResult,
+use crate::codegen::naming::{is_identifier, to_pascal_case, to_screaming_snake};
+use crate::codegen::placeholders::{
+    binds_by_name, empty_slice_sql, is_expanded_slice, mark_slices, rewrite_to_paramstyle,
+    slice_marker,
 };
-use std::fs;
-use std::path::Path;
+use crate::codegen::{Codegen, GeneratedFile};
+use crate::config::ParamStyle;
+use crate::query::{PlaceholderKind, QueryCardinality, QueryInputField, QueryParseResult};
+use crate::schema::SchemaParseResult;
+use std::path::PathBuf;
 
 const PYTHON_KEYWORDS: &[&str] = &[
     "False", "None", "True", "and", "as", "assert", "async", "await", "break", "class", "continue",
@@ -11,48 +16,14 @@ const PYTHON_KEYWORDS: &[&str] = &[
     "with", "yield",
 ];
 
+/// Rows fetched per round trip by `:iter` functions unless the caller passes
+/// `batch_size`.
+const ITER_BATCH_SIZE: usize = 1000;
+
 fn is_python_keyword(name: &str) -> bool {
     PYTHON_KEYWORDS.contains(&name)
 }
 
-/// Convert a `snake_case` or `PascalCase` name to `PascalCase`.
-fn to_pascal_case(name: &str) -> String {
-    if name.contains('_') {
-        name.split('_')
-            .filter(|s| !s.is_empty())
-            .map(|s| {
-                let mut c = s.chars();
-                match c.next() {
-                    None => String::new(),
-                    Some(first) => first.to_uppercase().collect::<String>() + c.as_str(),
-                }
-            })
-            .collect()
-    } else {
-        let mut c = name.chars();
-        match c.next() {
-            None => String::new(),
-            Some(first) => first.to_uppercase().collect::<String>() + c.as_str(),
-        }
-    }
-}
-
-/// Convert a name to `SCREAMING_SNAKE_CASE`.
-fn to_screaming_snake(name: &str) -> String {
-    if name.contains('_') {
-        name.to_uppercase()
-    } else {
-        let mut result = String::new();
-        for (i, c) in name.chars().enumerate() {
-            if c.is_uppercase() && i > 0 {
-                result.push('_');
-            }
-            result.push(c.to_uppercase().next().unwrap());
-        }
-        result
-    }
-}
-
 /// Sanitise a field name: append `_` if it is a Python keyword.
 fn sanitise_field_name(name: &str) -> String {
     if is_python_keyword(name) {
@@ -62,30 +33,105 @@ fn sanitise_field_name(name: &str) -> String {
     }
 }
 
+/// Wrap a Python type in `Optional[...]` when the value may be NULL.
+/// `Any` already admits `None` and is left as-is.
+fn python_type_annotation(py_type: &str, nullable: bool) -> String {
+    if nullable && py_type != "Any" {
+        format!("Optional[{}]", py_type)
+    } else {
+        py_type.to_string()
+    }
+}
+
 /// Collect non-typing stdlib imports needed for the given type strings.
 fn collect_stdlib_imports(types: &[&str]) -> Vec<&'static str> {
     let mut imports: Vec<&'static str> = Vec::new();
     if types.iter().any(|t| t.starts_with("datetime.")) {
         imports.push("import datetime");
     }
-    if types.iter().any(|t| *t == "Decimal") {
+    if types.contains(&"Decimal") {
         imports.push("from decimal import Decimal");
     }
     imports
 }
 
-/// Generate a `.py` file from a list of parsed queries and write it to `output_path`.
+pub struct PythonCodegen {
+    /// The driver's paramstyle. Without one the SQL is emitted as written
+    /// and the argument shape follows the placeholders.
+    pub paramstyle: Option<ParamStyle>,
+}
+
+impl Codegen for PythonCodegen {
+    fn file_extension(&self) -> &'static str {
+        "py"
+    }
+
+    fn validate_query_name(&self, name: &str) -> Result<(), String> {
+        if !is_identifier(name) {
+            return Err("is not a valid Python identifier".to_string());
+        }
+        if is_python_keyword(name) {
+            return Err("is a Python keyword and cannot be used as a function name".to_string());
+        }
+        Ok(())
+    }
+
+    fn generate(
+        &self,
+        queries: &[QueryParseResult],
+        _schema: &SchemaParseResult,
+        source_filename: &str,
+        stem: &str,
+    ) -> Result<Vec<GeneratedFile>, std::io::Error> {
+        Ok(vec![GeneratedFile {
+            path: PathBuf::from(format!("{}.{}", stem, self.file_extension())),
+            contents: generate_python_file(queries, source_filename, self.paramstyle)?,
+        }])
+    }
+}
+
+/// Generate the contents of a `.py` file from a list of parsed queries.
 pub fn generate_python_file(
     queries: &[QueryParseResult],
     source_filename: &str,
-    output_path: &Path,
-) -> Result<(), std::io::Error> {
+    paramstyle: Option<ParamStyle>,
+) -> Result<String, std::io::Error> {
     // Detect naming collisions
     let mut class_names_seen: Vec<String> = Vec::new();
     for query in queries {
-        if matches!(query.annotation.cardinality, QueryCardinality::Exec) {
+        // `$n` placeholders are numbered, so a list of values can't be
+        // spliced in without renumbering every later one.
+        let numbered = |f: &QueryInputField| match paramstyle {
+            Some(style) => matches!(style, ParamStyle::Numeric | ParamStyle::Dollar),
+            None => f.placeholder_kind == PlaceholderKind::Dollar,
+        };
+        if let Some(field) = query
+            .input_fields
+            .iter()
+            .find(|f| is_expanded_slice(f) && numbered(f))
+        {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!(
+                    "@slice parameter \"{}\" of \"{}\" in \"{}\" can't be expanded with numbered placeholders",
+                    field.name, query.annotation.name, source_filename
+                ),
+            ));
+        }
+        if !query.annotation.cardinality.has_row_type() {
             continue;
         }
+        if matches!(query.annotation.cardinality, QueryCardinality::Iter)
+            && query.input_fields.iter().any(|f| f.name == "batch_size")
+        {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!(
+                    "parameter \"batch_size\" of \"{}\" in \"{}\" clashes with the :iter batch size argument",
+                    query.annotation.name, source_filename
+                ),
+            ));
+        }
         let class_name = format!("{}Row", to_pascal_case(&query.annotation.name));
         if class_names_seen.contains(&class_name) {
             return Err(std::io::Error::new(
@@ -101,17 +147,26 @@ pub fn generate_python_file(
 
     // Gather all type strings to determine imports
     let mut all_types: Vec<String> = Vec::new();
-    let has_one = queries
-        .iter()
-        .any(|q| matches!(q.annotation.cardinality, QueryCardinality::One));
+    let has_nullable = queries.iter().any(|q| {
+        q.input_fields.iter().any(|f| f.nullable) || q.output_fields.iter().any(|f| f.nullable)
+    });
+    let has_optional = has_nullable
+        || queries.iter().any(|q| {
+            matches!(
+                q.annotation.cardinality,
+                QueryCardinality::One | QueryCardinality::Val | QueryCardinality::ExecLastId
+            )
+        });
 
     for query in queries {
         for f in &query.input_fields {
             all_types.push(f.data_type.clone());
         }
-        // Output field types are currently all Any (no data_type on QueryOutputField yet)
+        for f in &query.output_fields {
+            all_types.push(f.python_type.clone());
+        }
     }
-    if has_one {
+    if has_optional {
         all_types.push("Optional".to_string());
     }
 
@@ -136,10 +191,22 @@ pub fn generate_python_file(
     // Single consolidated typing import
     {
         let mut typing_names: Vec<&str> = vec!["Any"];
-        if has_one {
+        if queries
+            .iter()
+            .any(|q| matches!(q.annotation.cardinality, QueryCardinality::Iter))
+        {
+            typing_names.push("Iterator");
+        }
+        if has_optional {
             typing_names.push("Optional");
         }
         typing_names.push("Protocol");
+        if queries
+            .iter()
+            .any(|q| q.input_fields.iter().any(|f| f.slice.is_some()))
+        {
+            typing_names.push("Sequence");
+        }
         out.push_str(&format!("from typing import {}\n", typing_names.join(", ")));
     }
     out.push('\n');
@@ -149,6 +216,33 @@ pub fn generate_python_file(
     out.push_str("    def execute(self, sql: str, parameters: Any = ...) -> Any: ...\n");
     out.push_str("    def fetchone(self) -> tuple[Any, ...] | None: ...\n");
     out.push_str("    def fetchall(self) -> list[tuple[Any, ...]]: ...\n");
+    out.push_str("    def fetchmany(self, size: int = ...) -> list[tuple[Any, ...]]: ...\n");
+    // Only required of cursors when a query in the file reads them
+    if queries
+        .iter()
+        .any(|q| matches!(q.annotation.cardinality, QueryCardinality::ExecRows))
+    {
+        out.push_str("    @property\n");
+        out.push_str("    def rowcount(self) -> int: ...\n");
+    }
+    if queries
+        .iter()
+        .any(|q| matches!(q.annotation.cardinality, QueryCardinality::ExecLastId))
+    {
+        out.push_str("    @property\n");
+        out.push_str("    def lastrowid(self) -> int | None: ...\n");
+    }
+
+    // Exceptions raised by `:one!` functions
+    if queries
+        .iter()
+        .any(|q| matches!(q.annotation.cardinality, QueryCardinality::ExactlyOne))
+    {
+        out.push_str("\n\nclass NoRowsError(Exception):\n");
+        out.push_str("    \"\"\"Raised when a `:one!` query returns no rows.\"\"\"\n");
+        out.push_str("\n\nclass TooManyRowsError(Exception):\n");
+        out.push_str("    \"\"\"Raised when a `:one!` query returns more than one row.\"\"\"\n");
+    }
 
     // One block per query
     for query in queries {
@@ -163,21 +257,25 @@ pub fn generate_python_file(
         out.push_str(&format!("# {}\n", "-".repeat(75 - fn_name.len().min(73))));
 
         // SQL constant — strip trailing semicolon
-        let sql_text = query
-            .statement
-            .to_string()
-            .trim_end_matches(';')
-            .trim()
-            .to_string();
+        let rewritten = paramstyle
+            .map(|style| rewrite_to_paramstyle(&query.statement, &query.input_fields, style));
+        let sql_text = match &rewritten {
+            Some(rewritten) => rewritten.sql.clone(),
+            None => mark_slices(&query.statement, &query.input_fields)
+                .to_string()
+                .trim_end_matches(';')
+                .trim()
+                .to_string(),
+        };
         out.push_str(&format!(
             "\n{} = \"\"\"\n{}\n\"\"\"\n",
             const_name, sql_text
         ));
 
-        let is_exec = matches!(query.annotation.cardinality, QueryCardinality::Exec);
+        let has_row_type = query.annotation.cardinality.has_row_type();
 
-        // Row dataclass (only for :one and :many)
-        if !is_exec {
+        // Row dataclass (only for queries that return rows)
+        if has_row_type {
             out.push('\n');
             out.push('\n');
             out.push_str("@dataclasses.dataclass\n");
@@ -187,7 +285,7 @@ pub fn generate_python_file(
             } else {
                 for field in &query.output_fields {
                     let field_name = sanitise_field_name(&field.name);
-                    let py_type = output_field_python_type(field);
+                    let py_type = python_type_annotation(&field.python_type, field.nullable);
                     out.push_str(&format!("    {}: {}\n", field_name, py_type));
                 }
             }
@@ -195,8 +293,23 @@ pub fn generate_python_file(
 
         let return_type = match &query.annotation.cardinality {
             QueryCardinality::One => format!("Optional[{}]", row_class),
+            QueryCardinality::ExactlyOne => row_class.clone(),
+            QueryCardinality::Val => {
+                let field = &query.output_fields[0];
+                python_type_annotation(&field.python_type, true)
+            }
+            QueryCardinality::Column => {
+                let field = &query.output_fields[0];
+                format!(
+                    "list[{}]",
+                    python_type_annotation(&field.python_type, field.nullable)
+                )
+            }
             QueryCardinality::Many => format!("list[{}]", row_class),
+            QueryCardinality::Iter => format!("Iterator[{}]", row_class),
             QueryCardinality::Exec => "None".to_string(),
+            QueryCardinality::ExecRows => "int".to_string(),
+            QueryCardinality::ExecLastId => "Optional[int]".to_string(),
         };
 
         let mut params = vec!["cursor: _Cursor".to_string()];
@@ -204,8 +317,18 @@ pub fn generate_python_file(
             params.push("*".to_string());
             for f in &query.input_fields {
                 let param_name = sanitise_field_name(&f.name);
-                params.push(format!("{}: {}", param_name, f.data_type));
+                let mut py_type = python_type_annotation(&f.data_type, f.nullable);
+                if f.slice.is_some() {
+                    py_type = format!("Sequence[{}]", py_type);
+                }
+                params.push(format!("{}: {}", param_name, py_type));
+            }
+        }
+        if matches!(query.annotation.cardinality, QueryCardinality::Iter) {
+            if query.input_fields.is_empty() {
+                params.push("*".to_string());
             }
+            params.push(format!("batch_size: int = {}", ITER_BATCH_SIZE));
         }
 
         out.push('\n');
@@ -216,18 +339,73 @@ pub fn generate_python_file(
             return_type
         ));
 
-        let execute_args = build_execute_args(&query.input_fields);
+        // Splice one bind marker per value into the SQL for each expanded
+        // `@slice` parameter, or its `empty_slice_sql` for an empty list.
+        let mut sql_var = const_name.clone();
+        for field in query.input_fields.iter().filter(|f| is_expanded_slice(f)) {
+            let param_name = sanitise_field_name(&field.name);
+            let (placeholder, markers) = match (paramstyle, &field.placeholder_kind) {
+                (Some(ParamStyle::Qmark), _) | (None, PlaceholderKind::QuestionMark) => {
+                    ("?".to_string(), format!("\"?\" * len({})", param_name))
+                }
+                (Some(ParamStyle::Format), _) => {
+                    ("%s".to_string(), format!("[\"%s\"] * len({})", param_name))
+                }
+                (Some(ParamStyle::Pyformat), _) => (
+                    format!("%({})s", field.name),
+                    format!(
+                        "f\"%({}_{{i}})s\" for i in range(len({}))",
+                        field.name, param_name
+                    ),
+                ),
+                (Some(_), _) => (
+                    format!(":{}", field.name),
+                    format!(
+                        "f\":{}_{{i}}\" for i in range(len({}))",
+                        field.name, param_name
+                    ),
+                ),
+                (None, _) => (
+                    format!(":{}", field.placeholder_name),
+                    format!(
+                        "f\":{}_{{i}}\" for i in range(len({}))",
+                        field.placeholder_name, param_name
+                    ),
+                ),
+            };
+            out.push_str(&format!(
+                "    _sql = {}.replace(\"{}\", \", \".join({}) or \"{}\")\n",
+                sql_var,
+                slice_marker(field, &placeholder),
+                markers,
+                empty_slice_sql(field)
+            ));
+            sql_var = "_sql".to_string();
+        }
+
+        let execute_args = match (paramstyle, &rewritten) {
+            (Some(style), Some(rewritten)) => {
+                build_paramstyle_args(&query.input_fields, style, &rewritten.args)
+            }
+            _ => build_execute_args(&query.input_fields),
+        };
         if execute_args.is_empty() {
-            out.push_str(&format!("    cursor.execute({})\n", const_name));
+            out.push_str(&format!("    cursor.execute({})\n", sql_var));
         } else {
             out.push_str(&format!(
                 "    cursor.execute({}, {})\n",
-                const_name, execute_args
+                sql_var, execute_args
             ));
         }
 
         match &query.annotation.cardinality {
             QueryCardinality::Exec => {}
+            QueryCardinality::ExecRows => {
+                out.push_str("    return cursor.rowcount\n");
+            }
+            QueryCardinality::ExecLastId => {
+                out.push_str("    return cursor.lastrowid\n");
+            }
             QueryCardinality::One => {
                 out.push_str("    row = cursor.fetchone()\n");
                 out.push_str("    if row is None:\n");
@@ -239,6 +417,36 @@ pub fn generate_python_file(
                 }
                 out.push_str("    )\n");
             }
+            QueryCardinality::Val => {
+                out.push_str("    row = cursor.fetchone()\n");
+                out.push_str("    if row is None:\n");
+                out.push_str("        return None\n");
+                out.push_str("    return row[0]\n");
+            }
+            QueryCardinality::Column => {
+                out.push_str("    return [row[0] for row in cursor.fetchall()]\n");
+            }
+            QueryCardinality::ExactlyOne => {
+                // Two rows are enough to tell "exactly one" from "more".
+                out.push_str("    rows = cursor.fetchmany(2)\n");
+                out.push_str("    if not rows:\n");
+                out.push_str(&format!(
+                    "        raise NoRowsError(\"{} returned no rows\")\n",
+                    fn_name
+                ));
+                out.push_str("    if len(rows) > 1:\n");
+                out.push_str(&format!(
+                    "        raise TooManyRowsError(\"{} returned more than one row\")\n",
+                    fn_name
+                ));
+                out.push_str("    row = rows[0]\n");
+                out.push_str(&format!("    return {}(\n", row_class));
+                for (i, field) in query.output_fields.iter().enumerate() {
+                    let field_name = sanitise_field_name(&field.name);
+                    out.push_str(&format!("        {}=row[{}],\n", field_name, i));
+                }
+                out.push_str("    )\n");
+            }
             QueryCardinality::Many => {
                 out.push_str("    rows = cursor.fetchall()\n");
                 out.push_str(&format!("    return [\n        {}(\n", row_class));
@@ -248,12 +456,25 @@ pub fn generate_python_file(
                 }
                 out.push_str("        )\n        for row in rows\n    ]\n");
             }
+            QueryCardinality::Iter => {
+                out.push_str("    while True:\n");
+                out.push_str("        rows = cursor.fetchmany(batch_size)\n");
+                out.push_str("        if not rows:\n");
+                out.push_str("            return\n");
+                out.push_str("        for row in rows:\n");
+                out.push_str(&format!("            yield {}(\n", row_class));
+                for (i, field) in query.output_fields.iter().enumerate() {
+                    let field_name = sanitise_field_name(&field.name);
+                    out.push_str(&format!("                {}=row[{}],\n", field_name, i));
+                }
+                out.push_str("            )\n");
+            }
         }
     }
 
     out.push('\n');
 
-    fs::write(output_path, out)
+    Ok(out)
 }
 
 /// Build the second argument to `cursor.execute()`.
@@ -265,8 +486,8 @@ fn build_execute_args(input_fields: &[QueryInputField]) -> String {
         return String::new();
     }
 
-    // Use the placeholder_kind of the first field to decide the style.
-    // Mixed styles in one query are unusual; named wins unless all are positional.
+    // process_sql_statement rejects queries that mix placeholder kinds, so
+    // every field shares one kind.
     let all_positional = input_fields.iter().all(|f| {
         matches!(
             f.placeholder_kind,
@@ -277,7 +498,13 @@ fn build_execute_args(input_fields: &[QueryInputField]) -> String {
     if all_positional {
         let args: Vec<String> = input_fields
             .iter()
-            .map(|f| sanitise_field_name(&f.name))
+            .map(|f| {
+                if is_expanded_slice(f) {
+                    format!("*{}", sanitise_field_name(&f.name))
+                } else {
+                    sanitise_field_name(&f.name)
+                }
+            })
             .collect();
         if args.len() == 1 {
             format!("({},)", args[0])
@@ -290,15 +517,66 @@ fn build_execute_args(input_fields: &[QueryInputField]) -> String {
             .iter()
             .map(|f| {
                 let safe = sanitise_field_name(&f.name);
-                format!("\"{}\": {}", f.name, safe)
+                if is_expanded_slice(f) {
+                    // One key per value, matching the markers spliced into the SQL
+                    format!(
+                        "**{{f\"{}_{{i}}\": value for i, value in enumerate({})}}",
+                        f.placeholder_name, safe
+                    )
+                } else {
+                    format!("\"{}\": {}", f.placeholder_name, safe)
+                }
             })
             .collect();
         format!("{{{}}}", pairs.join(", "))
     }
 }
 
-/// Get the Python type annotation for an output field.
-/// Currently falls back to Any — output fields don't carry data_type yet.
-fn output_field_python_type(_field: &QueryOutputField) -> &'static str {
-    "Any"
+/// Build the second argument to `cursor.execute()` for SQL rewritten to
+/// `style`.
+///
+/// - `named` and `pyformat` → `{"name": name, ...}` dict
+/// - The other styles → a tuple of `args`, the names in bind order
+fn build_paramstyle_args(
+    input_fields: &[QueryInputField],
+    style: ParamStyle,
+    args: &[String],
+) -> String {
+    if input_fields.is_empty() {
+        return String::new();
+    }
+
+    if binds_by_name(style) {
+        let pairs: Vec<String> = input_fields
+            .iter()
+            .map(|f| {
+                let safe = sanitise_field_name(&f.name);
+                if is_expanded_slice(f) {
+                    format!(
+                        "**{{f\"{}_{{i}}\": value for i, value in enumerate({})}}",
+                        f.name, safe
+                    )
+                } else {
+                    format!("\"{}\": {}", f.name, safe)
+                }
+            })
+            .collect();
+        return format!("{{{}}}", pairs.join(", "));
+    }
+
+    let args: Vec<String> = args
+        .iter()
+        .map(|name| {
+            let safe = sanitise_field_name(name);
+            match input_fields.iter().find(|f| &f.name == name) {
+                Some(f) if is_expanded_slice(f) => format!("*{}", safe),
+                _ => safe,
+            }
+        })
+        .collect();
+    if args.len() == 1 {
+        format!("({},)", args[0])
+    } else {
+        format!("({})", args.join(", "))
+    }
 }
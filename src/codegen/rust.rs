@@ -0,0 +1,760 @@
+use crate::codegen::naming::{is_identifier, to_pascal_case, to_screaming_snake};
+use crate::codegen::placeholders::{
+    empty_slice_sql, is_expanded_slice, rewrite_placeholders, slice_marker, PositionalStyle,
+    RewrittenStatement,
+};
+use crate::codegen::{Codegen, GeneratedFile};
+use crate::query::{
+    classify_sql_type, PlaceholderKind, QueryCardinality, QueryInputField, QueryParseResult,
+    SqlTypeClass,
+};
+use crate::schema::SchemaParseResult;
+use std::path::PathBuf;
+
+const RUST_KEYWORDS: &[&str] = &[
+    "as", "async", "await", "break", "const", "continue", "crate", "dyn", "else", "enum", "extern",
+    "false", "fn", "for", "if", "impl", "in", "let", "loop", "match", "mod", "move", "mut", "pub",
+    "ref", "return", "self", "Self", "static", "struct", "super", "trait", "true", "type",
+    "unsafe", "use", "where", "while", "abstract", "become", "box", "do", "final", "gen", "macro",
+    "override", "priv", "try", "typeof", "unsized", "virtual", "yield",
+];
+
+fn is_rust_keyword(name: &str) -> bool {
+    RUST_KEYWORDS.contains(&name)
+}
+
+/// Sanitise an identifier: use a raw identifier (`r#type`) if it is a Rust keyword.
+/// `self`, `Self`, `super` and `crate` can't be raw identifiers, so they get a
+/// trailing `_` instead.
+fn sanitise_ident(name: &str) -> String {
+    match name {
+        "self" | "Self" | "super" | "crate" => format!("{}_", name),
+        _ if is_rust_keyword(name) => format!("r#{}", name),
+        _ => name.to_string(),
+    }
+}
+
+/// Names the generated functions use for their own locals and helpers.
+const RUST_GENERATED_NAMES: &[&str] = &["conn", "stmt", "sql", "values", "slice_markers"];
+
+/// Sanitise a parameter name: append `_` if it collides with a name the
+/// generated code uses, otherwise sanitise it as an identifier.
+fn sanitise_param_name(name: &str) -> String {
+    if RUST_GENERATED_NAMES.contains(&name) {
+        format!("{}_", name)
+    } else {
+        sanitise_ident(name)
+    }
+}
+
+/// Map a SQL type to the owned Rust type rusqlite reads it into.
+///
+/// Without rusqlite's optional `chrono`/`rust_decimal` features, dates and
+/// times come back as the `TEXT` SQLite stores them as, and decimals as `f64`.
+pub fn sql_type_to_rust(sql_type: &str) -> &'static str {
+    match classify_sql_type(sql_type) {
+        SqlTypeClass::Integer => "i64",
+        SqlTypeClass::Text
+        | SqlTypeClass::Uuid
+        | SqlTypeClass::Json
+        | SqlTypeClass::Date
+        | SqlTypeClass::Time
+        | SqlTypeClass::Timestamp => "String",
+        SqlTypeClass::Float | SqlTypeClass::Decimal => "f64",
+        SqlTypeClass::Boolean => "bool",
+        SqlTypeClass::Bytes => "Vec<u8>",
+        SqlTypeClass::Unknown => "Value",
+    }
+}
+
+/// The Rust type of a row field. Unresolved fields fall back to rusqlite's
+/// dynamically typed `Value`.
+fn output_rust_type(sql_type: Option<&str>, nullable: bool) -> String {
+    let rust_type = sql_type.map(sql_type_to_rust).unwrap_or("Value");
+    if nullable && rust_type != "Value" {
+        format!("Option<{}>", rust_type)
+    } else {
+        rust_type.to_string()
+    }
+}
+
+/// The Rust type of a function parameter: borrowed where the owned row type
+/// would force callers to allocate.
+fn input_rust_type(field: &QueryInputField) -> String {
+    let rust_type = match field.sql_type.as_deref().map(sql_type_to_rust) {
+        Some("String") => "&str",
+        Some("Vec<u8>") => "&[u8]",
+        Some("Value") | None => "&Value",
+        Some(other) => other,
+    };
+    if field.nullable && rust_type != "&Value" {
+        format!("Option<{}>", rust_type)
+    } else {
+        rust_type.to_string()
+    }
+}
+
+/// Quote `text` as a raw string literal, adding `#`s until the delimiter can't
+/// appear inside the text.
+fn raw_string_literal(text: &str) -> String {
+    let mut hashes = String::from("#");
+    while text.contains(&format!("\"{}", hashes)) {
+        hashes.push('#');
+    }
+    format!("r{}\"{}\"{}", hashes, text, hashes)
+}
+
+pub struct RustCodegen {
+    /// Bind-marker style to rewrite placeholders to. Without one the SQL
+    /// keeps the placeholders as written.
+    pub style: Option<PositionalStyle>,
+}
+
+impl Codegen for RustCodegen {
+    fn file_extension(&self) -> &'static str {
+        "rs"
+    }
+
+    /// Keywords are emitted as raw identifiers, so any identifier is accepted.
+    fn validate_query_name(&self, name: &str) -> Result<(), String> {
+        if !is_identifier(name) {
+            return Err("is not a valid Rust identifier".to_string());
+        }
+        Ok(())
+    }
+
+    fn generate(
+        &self,
+        queries: &[QueryParseResult],
+        _schema: &SchemaParseResult,
+        source_filename: &str,
+        stem: &str,
+    ) -> Result<Vec<GeneratedFile>, std::io::Error> {
+        Ok(vec![GeneratedFile {
+            path: PathBuf::from(format!("{}.{}", stem, self.file_extension())),
+            contents: generate_rust_file(queries, source_filename, self.style)?,
+        }])
+    }
+}
+
+/// Generate the contents of a `.rs` module from a list of parsed queries.
+///
+/// The module targets `rusqlite`: each query becomes a `const` SQL string, a
+/// row struct (for `:one`/`:many`) and a function taking a `&Connection`.
+pub fn generate_rust_file(
+    queries: &[QueryParseResult],
+    source_filename: &str,
+    style: Option<PositionalStyle>,
+) -> Result<String, std::io::Error> {
+    // Detect naming collisions
+    let mut struct_names_seen: Vec<String> = Vec::new();
+    for query in queries {
+        if let Some(field) = query.input_fields.iter().find(|f| f.slice.is_some()) {
+            let reason = if !is_expanded_slice(field) {
+                Some("rusqlite can't bind a list as an array")
+            } else if matches!(query.annotation.cardinality, QueryCardinality::Iter) {
+                Some(":iter functions take a statement prepared from fixed SQL")
+            } else if style == Some(PositionalStyle::Dollar) {
+                Some("it can't be expanded with $n placeholders")
+            } else {
+                None
+            };
+            if let Some(reason) = reason {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::InvalidInput,
+                    format!(
+                        "@slice parameter \"{}\" of \"{}\" in \"{}\" isn't supported: {}",
+                        field.name, query.annotation.name, source_filename, reason
+                    ),
+                ));
+            }
+        }
+        if !query.annotation.cardinality.has_row_type() {
+            continue;
+        }
+        let struct_name = format!("{}Row", to_pascal_case(&query.annotation.name));
+        if struct_names_seen.contains(&struct_name) {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!(
+                    "duplicate row struct name \"{}\" in \"{}\"",
+                    struct_name, source_filename
+                ),
+            ));
+        }
+        struct_names_seen.push(struct_name);
+    }
+
+    // Only import what the generated functions use, so the module compiles
+    // warning-free.
+    let mut imports: Vec<&str> = Vec::new();
+    // Queries with `@slice` parameters bind a list of values built at call
+    // time instead of using the parameter macros.
+    let has_slices = |q: &QueryParseResult| q.input_fields.iter().any(is_expanded_slice);
+    let binds_in_order = |q: &QueryParseResult| match style {
+        Some(style) => style == PositionalStyle::QuestionMark,
+        None => all_question_marks(&q.input_fields),
+    };
+    if queries
+        .iter()
+        .any(|q| !q.input_fields.is_empty() && !has_slices(q) && !binds_in_order(q))
+    {
+        imports.push("named_params");
+    }
+    if queries
+        .iter()
+        .any(|q| !q.input_fields.is_empty() && !has_slices(q) && binds_in_order(q))
+    {
+        imports.push("params");
+    }
+    let is_iter = |q: &QueryParseResult| matches!(q.annotation.cardinality, QueryCardinality::Iter);
+    if !queries.iter().all(is_iter) {
+        imports.push("Connection");
+    }
+    if queries.iter().any(|q| {
+        matches!(
+            q.annotation.cardinality,
+            QueryCardinality::One | QueryCardinality::Val
+        )
+    }) {
+        imports.push("OptionalExtension");
+    }
+    imports.push("Result");
+    if queries.iter().any(is_iter) {
+        imports.push("Statement");
+    }
+    if queries.iter().any(has_slices) {
+        imports.push("ToSql");
+    }
+
+    let uses_value = queries.iter().any(|q| {
+        q.input_fields
+            .iter()
+            .any(|f| input_rust_type(f) == "&Value")
+            || (!q.annotation.cardinality.is_exec()
+                && q.output_fields
+                    .iter()
+                    .any(|f| output_rust_type(f.sql_type.as_deref(), f.nullable) == "Value"))
+    });
+
+    let mut out = String::new();
+
+    // Header
+    out.push_str(&format!(
+        "// GENERATED BY icantbelieveitsnotsql -- DO NOT EDIT\n// Source: {}\n\n",
+        source_filename
+    ));
+
+    out.push_str(&format!("use rusqlite::{{{}}};\n", imports.join(", ")));
+    if uses_value {
+        out.push_str("use rusqlite::types::Value;\n");
+    }
+    if queries.iter().any(has_slices) {
+        out.push_str("\n/// `n` comma-separated `?` markers, or `empty` when there are none.\n");
+        out.push_str("fn slice_markers(n: usize, empty: &str) -> String {\n");
+        out.push_str("    if n == 0 {\n        return empty.to_string();\n    }\n");
+        out.push_str("    vec![\"?\"; n].join(\", \")\n");
+        out.push_str("}\n");
+    }
+
+    // One block per query
+    for query in queries {
+        let fn_name = sanitise_ident(&query.annotation.name.to_lowercase());
+        let pascal_name = to_pascal_case(&query.annotation.name);
+        let const_name = format!("{}_SQL", to_screaming_snake(&query.annotation.name));
+        let row_struct = format!("{}Row", pascal_name);
+
+        out.push('\n');
+        out.push_str(&format!("// {}\n", "-".repeat(75 - fn_name.len().min(73))));
+        out.push_str(&format!("// {}\n", fn_name));
+        out.push_str(&format!("// {}\n", "-".repeat(75 - fn_name.len().min(73))));
+
+        // SQL constant — strip trailing semicolon. With `@slice` parameters
+        // the SQL is rewritten to `?` markers so values can be bound as a list.
+        let rewritten = if has_slices(query) {
+            Some(rewrite_placeholders(
+                &query.statement,
+                &query.input_fields,
+                PositionalStyle::QuestionMark,
+            ))
+        } else {
+            style.map(|style| rewrite_placeholders(&query.statement, &query.input_fields, style))
+        };
+        let sql_text = match &rewritten {
+            Some(rewritten) => rewritten.sql.clone(),
+            None => query
+                .statement
+                .to_string()
+                .trim_end_matches(';')
+                .trim()
+                .to_string(),
+        };
+        out.push_str(&format!(
+            "\npub const {}: &str = {};\n",
+            const_name,
+            raw_string_literal(&sql_text)
+        ));
+
+        let has_row_type = query.annotation.cardinality.has_row_type();
+
+        // Row struct (only for queries that return rows)
+        if has_row_type {
+            out.push('\n');
+            out.push_str("#[derive(Debug, Clone, PartialEq)]\n");
+            if query.output_fields.is_empty() {
+                out.push_str(&format!("pub struct {} {{}}\n", row_struct));
+            } else {
+                out.push_str(&format!("pub struct {} {{\n", row_struct));
+                for field in &query.output_fields {
+                    let field_name = sanitise_ident(&field.name);
+                    let rust_type = output_rust_type(field.sql_type.as_deref(), field.nullable);
+                    out.push_str(&format!("    pub {}: {},\n", field_name, rust_type));
+                }
+                out.push_str("}\n");
+            }
+        }
+
+        let return_type = match &query.annotation.cardinality {
+            QueryCardinality::One => format!("Result<Option<{}>>", row_struct),
+            QueryCardinality::ExactlyOne => format!("Result<{}>", row_struct),
+            // `None` for no row; a nullable column nests a second `Option`.
+            QueryCardinality::Val => format!("Result<Option<{}>>", value_type(query)),
+            QueryCardinality::Column => format!("Result<Vec<{}>>", value_type(query)),
+            QueryCardinality::Many => format!("Result<Vec<{}>>", row_struct),
+            QueryCardinality::Iter => {
+                format!("Result<impl Iterator<Item = Result<{}>> + 's>", row_struct)
+            }
+            QueryCardinality::Exec | QueryCardinality::ExecRows => "Result<usize>".to_string(),
+            QueryCardinality::ExecLastId => "Result<i64>".to_string(),
+        };
+
+        // The rows borrow the statement, so an `:iter` function takes one the
+        // caller prepared from the SQL constant instead of a connection.
+        let is_iter = is_iter(query);
+        let mut params = vec![if is_iter {
+            "stmt: &'s mut Statement<'_>".to_string()
+        } else {
+            "conn: &Connection".to_string()
+        }];
+        for f in &query.input_fields {
+            params.push(format!(
+                "{}: {}",
+                sanitise_param_name(&f.name),
+                if f.slice.is_some() {
+                    slice_rust_type(f)
+                } else {
+                    input_rust_type(f)
+                }
+            ));
+        }
+
+        out.push('\n');
+        if is_iter {
+            out.push_str(&format!(
+                "/// Prepare `stmt` from [`{}`]; rows are read as the iterator advances.\n",
+                const_name
+            ));
+        }
+        out.push_str(&format!(
+            "pub fn {}{}({}) -> {} {{\n",
+            fn_name,
+            if is_iter { "<'s>" } else { "" },
+            params.join(", "),
+            return_type
+        ));
+        let bind_args = match (&rewritten, style) {
+            (Some(rewritten), _) if has_slices(query) => {
+                push_slice_bindings(&mut out, query, &const_name, &rewritten.args);
+                "values.as_slice()".to_string()
+            }
+            (rewritten, style) => {
+                if !is_iter {
+                    out.push_str(&format!(
+                        "    let mut stmt = conn.prepare_cached({})?;\n",
+                        const_name
+                    ));
+                }
+                match (rewritten, style) {
+                    (Some(rewritten), Some(style)) => build_positional_bind_args(style, rewritten),
+                    _ => build_bind_args(&query.input_fields),
+                }
+            }
+        };
+
+        match &query.annotation.cardinality {
+            QueryCardinality::Exec | QueryCardinality::ExecRows => {
+                out.push_str(&format!("    stmt.execute({})\n", bind_args));
+            }
+            QueryCardinality::ExecLastId => {
+                out.push_str(&format!("    stmt.execute({})?;\n", bind_args));
+                out.push_str("    Ok(conn.last_insert_rowid())\n");
+            }
+            QueryCardinality::One => {
+                out.push_str(&format!("    stmt.query_row({}, |row| {{\n", bind_args));
+                push_row_constructor(&mut out, &row_struct, query, "        ");
+                out.push_str("    })\n");
+                out.push_str("    .optional()\n");
+            }
+            QueryCardinality::Val => {
+                out.push_str(&format!(
+                    "    stmt.query_row({}, |row| row.get(0)).optional()\n",
+                    bind_args
+                ));
+            }
+            QueryCardinality::Column => {
+                out.push_str(&format!(
+                    "    let rows = stmt.query_map({}, |row| row.get(0))?;\n",
+                    bind_args
+                ));
+                out.push_str("    rows.collect()\n");
+            }
+            QueryCardinality::ExactlyOne => {
+                // Fails with `QueryReturnedNoRows` or `QueryReturnedMoreThanOneRow`.
+                out.push_str(&format!("    stmt.query_one({}, |row| {{\n", bind_args));
+                push_row_constructor(&mut out, &row_struct, query, "        ");
+                out.push_str("    })\n");
+            }
+            QueryCardinality::Many => {
+                out.push_str(&format!(
+                    "    let rows = stmt.query_map({}, |row| {{\n",
+                    bind_args
+                ));
+                push_row_constructor(&mut out, &row_struct, query, "        ");
+                out.push_str("    })?;\n");
+                out.push_str("    rows.collect()\n");
+            }
+            QueryCardinality::Iter => {
+                out.push_str(&format!(
+                    "    let rows = stmt.query_map({}, |row| {{\n",
+                    bind_args
+                ));
+                push_row_constructor(&mut out, &row_struct, query, "        ");
+                out.push_str("    })?;\n");
+                out.push_str("    Ok(rows)\n");
+            }
+        }
+        out.push_str("}\n");
+    }
+
+    Ok(out)
+}
+
+/// The Rust type of the single column a `:val` or `:column` query returns.
+fn value_type(query: &QueryParseResult) -> String {
+    let field = &query.output_fields[0];
+    output_rust_type(field.sql_type.as_deref(), field.nullable)
+}
+
+/// The Rust type of a `@slice` parameter: a slice of its values.
+fn slice_rust_type(field: &QueryInputField) -> String {
+    match input_rust_type(field).as_str() {
+        "&Value" => "&[Value]".to_string(),
+        element => format!("&[{}]", element),
+    }
+}
+
+/// Emit the statement preparation for a query with `@slice` parameters: its
+/// markers are spliced into the SQL, then `values` collects the arguments
+/// in bind order with each list flattened in place.
+fn push_slice_bindings(
+    out: &mut String,
+    query: &QueryParseResult,
+    const_name: &str,
+    args: &[String],
+) {
+    let mut sql_var = const_name.to_string();
+    for field in query.input_fields.iter().filter(|f| is_expanded_slice(f)) {
+        out.push_str(&format!(
+            "    let sql = {}.replace(\"{}\", &slice_markers({}.len(), \"{}\"));\n",
+            sql_var,
+            slice_marker(field, "?"),
+            sanitise_param_name(&field.name),
+            empty_slice_sql(field)
+        ));
+        sql_var = "sql".to_string();
+    }
+    out.push_str(&format!(
+        "    let mut stmt = conn.prepare_cached(&{})?;\n",
+        sql_var
+    ));
+    out.push_str("    let mut values: Vec<&dyn ToSql> = Vec::new();\n");
+    for name in args {
+        let ident = sanitise_param_name(name);
+        let is_slice = query
+            .input_fields
+            .iter()
+            .any(|f| &f.name == name && is_expanded_slice(f));
+        if is_slice {
+            out.push_str(&format!(
+                "    values.extend({}.iter().map(|value| value as &dyn ToSql));\n",
+                ident
+            ));
+        } else {
+            out.push_str(&format!("    values.push(&{});\n", ident));
+        }
+    }
+}
+
+/// Emit `Ok(Row { field: row.get(i)?, ... })` for a row-mapping closure body.
+fn push_row_constructor(
+    out: &mut String,
+    row_struct: &str,
+    query: &QueryParseResult,
+    indent: &str,
+) {
+    if query.output_fields.is_empty() {
+        out.push_str(&format!("{}Ok({} {{}})\n", indent, row_struct));
+        return;
+    }
+    out.push_str(&format!("{}Ok({} {{\n", indent, row_struct));
+    for (i, field) in query.output_fields.iter().enumerate() {
+        let field_name = sanitise_ident(&field.name);
+        out.push_str(&format!("{}    {}: row.get({})?,\n", indent, field_name, i));
+    }
+    out.push_str(&format!("{}}})\n", indent));
+}
+
+fn all_question_marks(input_fields: &[QueryInputField]) -> bool {
+    input_fields
+        .iter()
+        .all(|f| f.placeholder_kind == PlaceholderKind::QuestionMark)
+}
+
+/// Build the parameter argument passed to rusqlite.
+///
+/// - No params → `[]`
+/// - Anonymous (`?`) params → `params![p1, p2]`
+/// - Named (`:name`) and dollar (`$1`) params → `named_params! { ":name": name, ... }`.
+///   SQLite treats `$1` as a parameter named `$1`, not a position.
+fn build_bind_args(input_fields: &[QueryInputField]) -> String {
+    if input_fields.is_empty() {
+        return "[]".to_string();
+    }
+
+    if all_question_marks(input_fields) {
+        let args: Vec<String> = input_fields
+            .iter()
+            .map(|f| sanitise_param_name(&f.name))
+            .collect();
+        format!("params![{}]", args.join(", "))
+    } else {
+        let pairs: Vec<String> = input_fields
+            .iter()
+            .map(|f| {
+                let key = match f.placeholder_kind {
+                    PlaceholderKind::Dollar => {
+                        format!("${}", f.placeholder_name.trim_start_matches('p'))
+                    }
+                    _ => format!(":{}", f.placeholder_name),
+                };
+                format!("\"{}\": {}", key, sanitise_param_name(&f.name))
+            })
+            .collect();
+        format!("named_params! {{ {} }}", pairs.join(", "))
+    }
+}
+
+/// Build the parameter argument for SQL rewritten to `style`.
+///
+/// `?` markers are bound in order, repeating a parameter that is referenced
+/// twice; `$n` markers are bound by name, as `named_params! { "$1": ... }`.
+fn build_positional_bind_args(style: PositionalStyle, rewritten: &RewrittenStatement) -> String {
+    if rewritten.args.is_empty() {
+        return "[]".to_string();
+    }
+
+    let args = rewritten.args.iter().map(|name| sanitise_param_name(name));
+    match style {
+        PositionalStyle::QuestionMark => {
+            format!("params![{}]", args.collect::<Vec<_>>().join(", "))
+        }
+        PositionalStyle::Dollar => {
+            let pairs: Vec<String> = args
+                .enumerate()
+                .map(|(i, ident)| format!("\"${}\": {}", i + 1, ident))
+                .collect();
+            format!("named_params! {{ {} }}", pairs.join(", "))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::codegen::test_support::parse_queries;
+
+    #[test]
+    fn test_generate_rust_file_cardinalities() {
+        let queries = parse_queries(
+            "-- get_user :one
+             SELECT id, email, bio FROM users WHERE id = :id;
+
+             -- get_user_by_email :one!
+             SELECT id, email FROM users WHERE email = :email;
+
+             -- list_users :many
+             SELECT id, email FROM users ORDER BY id;
+
+             -- get_email :val
+             SELECT email FROM users WHERE id = :id;
+
+             -- list_emails :column
+             SELECT email FROM users ORDER BY id;
+
+             -- iter_users_after :iter
+             SELECT id, email FROM users WHERE id > :after_id ORDER BY id;
+
+             -- delete_user :exec
+             DELETE FROM users WHERE id = :id;
+
+             -- clear_bio :execrows
+             UPDATE users SET bio = NULL WHERE bio = :bio;
+
+             -- create_user :execlastid
+             INSERT INTO users (email) VALUES (:email);",
+        );
+        let contents = generate_rust_file(&queries, "users.sql", None).unwrap();
+
+        let expected = [
+            "use rusqlite::{named_params, Connection, OptionalExtension, Result, Statement};\n",
+            r##"
+pub const GET_USER_SQL: &str = r#"SELECT id, email, bio FROM users WHERE id = :id"#;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct GetUserRow {
+    pub id: i64,
+    pub email: String,
+    pub bio: Option<String>,
+}
+
+pub fn get_user(conn: &Connection, id: i64) -> Result<Option<GetUserRow>> {
+    let mut stmt = conn.prepare_cached(GET_USER_SQL)?;
+    stmt.query_row(named_params! { ":id": id }, |row| {
+        Ok(GetUserRow {
+            id: row.get(0)?,
+            email: row.get(1)?,
+            bio: row.get(2)?,
+        })
+    })
+    .optional()
+}
+"##,
+            r#"
+pub fn get_user_by_email(conn: &Connection, email: &str) -> Result<GetUserByEmailRow> {
+    let mut stmt = conn.prepare_cached(GET_USER_BY_EMAIL_SQL)?;
+    stmt.query_one(named_params! { ":email": email }, |row| {
+        Ok(GetUserByEmailRow {
+            id: row.get(0)?,
+            email: row.get(1)?,
+        })
+    })
+}
+"#,
+            r#"
+pub fn list_users(conn: &Connection) -> Result<Vec<ListUsersRow>> {
+    let mut stmt = conn.prepare_cached(LIST_USERS_SQL)?;
+    let rows = stmt.query_map([], |row| {
+        Ok(ListUsersRow {
+            id: row.get(0)?,
+            email: row.get(1)?,
+        })
+    })?;
+    rows.collect()
+}
+"#,
+            r#"
+pub fn get_email(conn: &Connection, id: i64) -> Result<Option<String>> {
+    let mut stmt = conn.prepare_cached(GET_EMAIL_SQL)?;
+    stmt.query_row(named_params! { ":id": id }, |row| row.get(0)).optional()
+}
+"#,
+            r#"
+pub fn list_emails(conn: &Connection) -> Result<Vec<String>> {
+    let mut stmt = conn.prepare_cached(LIST_EMAILS_SQL)?;
+    let rows = stmt.query_map([], |row| row.get(0))?;
+    rows.collect()
+}
+"#,
+            r#"
+/// Prepare `stmt` from [`ITER_USERS_AFTER_SQL`]; rows are read as the iterator advances.
+pub fn iter_users_after<'s>(stmt: &'s mut Statement<'_>, after_id: i64) -> Result<impl Iterator<Item = Result<IterUsersAfterRow>> + 's> {
+    let rows = stmt.query_map(named_params! { ":after_id": after_id }, |row| {
+        Ok(IterUsersAfterRow {
+            id: row.get(0)?,
+            email: row.get(1)?,
+        })
+    })?;
+    Ok(rows)
+}
+"#,
+            r#"
+pub fn delete_user(conn: &Connection, id: i64) -> Result<usize> {
+    let mut stmt = conn.prepare_cached(DELETE_USER_SQL)?;
+    stmt.execute(named_params! { ":id": id })
+}
+"#,
+            r#"
+pub fn clear_bio(conn: &Connection, bio: Option<&str>) -> Result<usize> {
+    let mut stmt = conn.prepare_cached(CLEAR_BIO_SQL)?;
+    stmt.execute(named_params! { ":bio": bio })
+}
+"#,
+            r#"
+pub fn create_user(conn: &Connection, email: &str) -> Result<i64> {
+    let mut stmt = conn.prepare_cached(CREATE_USER_SQL)?;
+    stmt.execute(named_params! { ":email": email })?;
+    Ok(conn.last_insert_rowid())
+}
+"#,
+        ];
+        for snippet in expected {
+            assert!(
+                contents.contains(snippet),
+                "missing:\n{}\nin:\n{}",
+                snippet,
+                contents
+            );
+        }
+    }
+
+    #[test]
+    fn test_generate_rust_file_positional_styles() {
+        let queries = parse_queries(
+            "-- find_user :one
+             SELECT id, email FROM users WHERE email = :email OR bio = :email AND id > :id;",
+        );
+
+        let qmark =
+            generate_rust_file(&queries, "users.sql", Some(PositionalStyle::QuestionMark)).unwrap();
+        assert!(qmark.contains(
+            r##"r#"SELECT id, email FROM users WHERE email = ? OR bio = ? AND id > ?"#"##
+        ));
+        assert!(qmark.contains("stmt.query_row(params![email, email, id], |row| {"));
+
+        let dollar =
+            generate_rust_file(&queries, "users.sql", Some(PositionalStyle::Dollar)).unwrap();
+        assert!(dollar.contains(
+            r##"r#"SELECT id, email FROM users WHERE email = $1 OR bio = $1 AND id > $2"#"##
+        ));
+        assert!(
+            dollar.contains(r#"stmt.query_row(named_params! { "$1": email, "$2": id }, |row| {"#)
+        );
+    }
+
+    #[test]
+    fn test_generate_rust_file_reserved_param_names() {
+        let queries = parse_queries(
+            "-- find_users :column
+             -- @slice values
+             SELECT id FROM users WHERE email = :conn AND bio = :stmt AND id IN (:values);",
+        );
+
+        let contents =
+            generate_rust_file(&queries, "users.sql", Some(PositionalStyle::QuestionMark)).unwrap();
+        assert!(contents.contains(
+            "pub fn find_users(conn: &Connection, conn_: &str, stmt_: Option<&str>, values_: &[i64]) -> Result<Vec<i64>> {"
+        ));
+        assert!(contents.contains("    values.push(&conn_);\n    values.push(&stmt_);\n"));
+        assert!(contents.contains("    values.extend(values_.iter()"));
+    }
+}
@@ -0,0 +1,24 @@
+//! Fixtures shared by the backends' tests.
+
+use crate::query::{extract_query_annotations, process_sql_statement, QueryParseResult};
+use crate::schema::SchemaParseResult;
+use sqlparser::dialect::SQLiteDialect;
+use sqlparser::parser::Parser as SQLParser;
+
+/// Process the annotated queries in `sql` against a `users` table.
+pub fn parse_queries(sql: &str) -> Vec<QueryParseResult> {
+    let mut schema = SchemaParseResult::default();
+    schema
+        .apply_sql(
+            "CREATE TABLE users (id INTEGER PRIMARY KEY, email TEXT NOT NULL, bio TEXT);",
+            &SQLiteDialect {},
+        )
+        .unwrap();
+    let ast = SQLParser::parse_sql(&SQLiteDialect {}, sql).unwrap();
+    ast.iter()
+        .zip(extract_query_annotations(sql))
+        .map(|(statement, annotation)| {
+            process_sql_statement(statement, annotation.unwrap(), &schema).unwrap()
+        })
+        .collect()
+}
@@ -0,0 +1,984 @@
+use crate::codegen::naming::{is_identifier, to_camel_case, to_pascal_case};
+use crate::codegen::placeholders::{
+    empty_slice_sql, is_expanded_slice, rewrite_placeholders, slice_marker, PositionalStyle,
+};
+use crate::codegen::{Codegen, GeneratedFile};
+use crate::config::TypeScriptClient;
+use crate::query::{classify_sql_type, QueryCardinality, QueryParseResult, SqlTypeClass};
+use crate::schema::SchemaParseResult;
+use std::path::PathBuf;
+
+const TYPESCRIPT_RESERVED_WORDS: &[&str] = &[
+    "break",
+    "case",
+    "catch",
+    "class",
+    "const",
+    "continue",
+    "debugger",
+    "default",
+    "delete",
+    "do",
+    "else",
+    "enum",
+    "export",
+    "extends",
+    "false",
+    "finally",
+    "for",
+    "function",
+    "if",
+    "import",
+    "in",
+    "instanceof",
+    "new",
+    "null",
+    "return",
+    "super",
+    "switch",
+    "this",
+    "throw",
+    "true",
+    "try",
+    "typeof",
+    "var",
+    "void",
+    "while",
+    "with",
+    "implements",
+    "interface",
+    "let",
+    "package",
+    "private",
+    "protected",
+    "public",
+    "static",
+    "yield",
+    "await",
+];
+
+fn is_typescript_reserved_word(name: &str) -> bool {
+    TYPESCRIPT_RESERVED_WORDS.contains(&name)
+}
+
+/// Sanitise a binding name: append `_` if it is a reserved word. Property
+/// names may be reserved words, so only function names need this.
+fn sanitise_ident(name: &str) -> String {
+    if is_typescript_reserved_word(name) {
+        format!("{}_", name)
+    } else {
+        name.to_string()
+    }
+}
+
+/// A property name as written in an interface or object literal. Column
+/// names that aren't identifiers, like `COUNT(*)`, are quoted.
+fn property_name(name: &str) -> String {
+    if is_identifier(name) {
+        name.to_string()
+    } else {
+        serde_json::to_string(name).unwrap()
+    }
+}
+
+/// Access property `name` of `object`, with a bracket when `name` isn't an
+/// identifier.
+fn property_access(object: &str, name: &str) -> String {
+    if is_identifier(name) {
+        format!("{}.{}", object, name)
+    } else {
+        format!("{}[{}]", object, property_name(name))
+    }
+}
+
+/// Map a SQL type to the TypeScript type the given client decodes it into.
+///
+/// node-postgres parses dates and timestamps into `Date` but leaves `NUMERIC`
+/// and 64-bit integers as strings to avoid losing precision. better-sqlite3
+/// returns SQLite's storage classes: dates are `TEXT` and booleans `INTEGER`.
+pub fn sql_type_to_typescript(sql_type: &str, client: TypeScriptClient) -> &'static str {
+    let class = classify_sql_type(sql_type);
+
+    match client {
+        TypeScriptClient::Pg => {
+            let upper = sql_type.trim().to_uppercase();
+            if upper == "BIGINT" || upper == "INT8" {
+                return "string";
+            }
+            match class {
+                SqlTypeClass::Integer | SqlTypeClass::Float => "number",
+                SqlTypeClass::Text | SqlTypeClass::Uuid | SqlTypeClass::Time => "string",
+                SqlTypeClass::Decimal => "string",
+                SqlTypeClass::Boolean => "boolean",
+                SqlTypeClass::Bytes => "Buffer",
+                SqlTypeClass::Date | SqlTypeClass::Timestamp => "Date",
+                SqlTypeClass::Json | SqlTypeClass::Unknown => "unknown",
+            }
+        }
+        TypeScriptClient::BetterSqlite3 => match class {
+            SqlTypeClass::Integer
+            | SqlTypeClass::Float
+            | SqlTypeClass::Decimal
+            | SqlTypeClass::Boolean => "number",
+            SqlTypeClass::Text
+            | SqlTypeClass::Uuid
+            | SqlTypeClass::Json
+            | SqlTypeClass::Date
+            | SqlTypeClass::Time
+            | SqlTypeClass::Timestamp => "string",
+            SqlTypeClass::Bytes => "Buffer",
+            SqlTypeClass::Unknown => "unknown",
+        },
+    }
+}
+
+fn typescript_type(sql_type: Option<&str>, nullable: bool, client: TypeScriptClient) -> String {
+    let ts_type = sql_type
+        .map(|t| sql_type_to_typescript(t, client))
+        .unwrap_or("unknown");
+    if nullable && ts_type != "unknown" {
+        format!("{} | null", ts_type)
+    } else {
+        ts_type.to_string()
+    }
+}
+
+/// Quote `text` as a template literal.
+fn template_literal(text: &str) -> String {
+    let escaped = text
+        .replace('\\', "\\\\")
+        .replace('`', "\\`")
+        .replace("${", "\\${");
+    format!("`{}`", escaped)
+}
+
+pub struct TypeScriptCodegen {
+    pub client: TypeScriptClient,
+}
+
+impl Codegen for TypeScriptCodegen {
+    fn file_extension(&self) -> &'static str {
+        "ts"
+    }
+
+    /// Reserved words get a trailing `_`, so any identifier is accepted.
+    fn validate_query_name(&self, name: &str) -> Result<(), String> {
+        if !is_identifier(name) {
+            return Err("is not a valid TypeScript identifier".to_string());
+        }
+        Ok(())
+    }
+
+    fn generate(
+        &self,
+        queries: &[QueryParseResult],
+        _schema: &SchemaParseResult,
+        source_filename: &str,
+        stem: &str,
+    ) -> Result<Vec<GeneratedFile>, std::io::Error> {
+        Ok(vec![GeneratedFile {
+            path: PathBuf::from(format!("{}.{}", stem, self.file_extension())),
+            contents: generate_typescript_file(queries, source_filename, self.client)?,
+        }])
+    }
+}
+
+/// Generate the contents of a `.ts` module from a list of parsed queries.
+///
+/// Each query becomes a SQL constant, row and parameter interfaces, and an
+/// async function. The client is described by a structural interface so the
+/// module has no import of `pg` or `better-sqlite3`.
+pub fn generate_typescript_file(
+    queries: &[QueryParseResult],
+    source_filename: &str,
+    client: TypeScriptClient,
+) -> Result<String, std::io::Error> {
+    // Detect naming collisions
+    let mut fn_names_seen: Vec<String> = Vec::new();
+    for query in queries {
+        let fn_name = to_camel_case(&query.annotation.name);
+        if fn_names_seen.contains(&fn_name) {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!(
+                    "duplicate function name \"{}\" in \"{}\"",
+                    fn_name, source_filename
+                ),
+            ));
+        }
+        fn_names_seen.push(fn_name);
+
+        // `$n` markers can't be expanded at call time; PostgreSQL binds the
+        // list as an array instead.
+        if client == TypeScriptClient::Pg {
+            if let Some(field) = query.input_fields.iter().find(|f| is_expanded_slice(f)) {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::InvalidInput,
+                    format!(
+                        "\"{}\" in \"{}\": @slice parameter \"{}\" needs dialect = \"postgresql\" \
+                         with node-postgres",
+                        query.annotation.name, source_filename, field.name
+                    ),
+                ));
+            }
+        }
+
+        // node-postgres reports no insert id; PostgreSQL returns ids through
+        // `RETURNING`.
+        if client == TypeScriptClient::Pg
+            && matches!(query.annotation.cardinality, QueryCardinality::ExecLastId)
+        {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!(
+                    "\"{}\" in \"{}\": :execlastid is not supported by node-postgres, \
+                     use INSERT ... RETURNING with :one instead",
+                    query.annotation.name, source_filename
+                ),
+            ));
+        }
+    }
+
+    let mut out = String::new();
+
+    // Header
+    out.push_str(&format!(
+        "// GENERATED BY icantbelieveitsnotsql -- DO NOT EDIT\n// Source: {}\n\n",
+        source_filename
+    ));
+
+    // Structural client interface
+    let client_type = match client {
+        TypeScriptClient::Pg => {
+            out.push_str("export interface Queryable {\n");
+            out.push_str(
+                "  query(config: { text: string; values?: unknown[]; rowMode: \"array\" }): \
+                 Promise<{ rows: unknown[][]; rowCount: number | null }>;\n",
+            );
+            out.push_str("}\n");
+            "Queryable"
+        }
+        TypeScriptClient::BetterSqlite3 => {
+            out.push_str("export interface Statement {\n");
+            out.push_str("  raw(toggle?: boolean): Statement;\n");
+            out.push_str("  get(...params: unknown[]): unknown;\n");
+            out.push_str("  all(...params: unknown[]): unknown[];\n");
+            out.push_str("  iterate(...params: unknown[]): IterableIterator<unknown>;\n");
+            out.push_str(
+                "  run(...params: unknown[]): { changes: number; lastInsertRowid: number | bigint };\n",
+            );
+            out.push_str("}\n\n");
+            out.push_str("export interface Database {\n");
+            out.push_str("  prepare(source: string): Statement;\n");
+            out.push_str("}\n");
+            "Database"
+        }
+    };
+
+    // Errors thrown by `:one!` functions
+    if queries
+        .iter()
+        .any(|q| matches!(q.annotation.cardinality, QueryCardinality::ExactlyOne))
+    {
+        for (class, message) in [
+            ("NoRowsError", "returned no rows"),
+            ("TooManyRowsError", "returned more than one row"),
+        ] {
+            out.push_str(&format!("\nexport class {} extends Error {{\n", class));
+            out.push_str("  constructor(query: string) {\n");
+            out.push_str(&format!("    super(`${{query}} {}`);\n", message));
+            out.push_str(&format!("    this.name = \"{}\";\n", class));
+            out.push_str("  }\n}\n");
+        }
+    }
+
+    // `:iter` on node-postgres streams through a server-side cursor, and each
+    // call needs a cursor name of its own.
+    if client == TypeScriptClient::Pg
+        && queries
+            .iter()
+            .any(|q| matches!(q.annotation.cardinality, QueryCardinality::Iter))
+    {
+        out.push_str("\nlet cursorCount = 0;\n");
+    }
+
+    // One block per query
+    for query in queries {
+        let fn_name = sanitise_ident(&to_camel_case(&query.annotation.name));
+        let pascal_name = to_pascal_case(&query.annotation.name);
+        let const_name = format!("{}Sql", to_camel_case(&query.annotation.name));
+        let row_interface = format!("{}Row", pascal_name);
+        let params_interface = format!("{}Params", pascal_name);
+
+        out.push('\n');
+        out.push_str(&format!("// {}\n", "-".repeat(75 - fn_name.len().min(73))));
+        out.push_str(&format!("// {}\n", fn_name));
+        out.push_str(&format!("// {}\n", "-".repeat(75 - fn_name.len().min(73))));
+
+        // node-postgres only understands `$n`; better-sqlite3 binds `?` from
+        // an argument list.
+        let style = match client {
+            TypeScriptClient::Pg => PositionalStyle::Dollar,
+            TypeScriptClient::BetterSqlite3 => PositionalStyle::QuestionMark,
+        };
+        let rewritten = rewrite_placeholders(&query.statement, &query.input_fields, style);
+        out.push_str(&format!(
+            "\nconst {} = {};\n",
+            const_name,
+            template_literal(&rewritten.sql)
+        ));
+
+        let has_row_type = query.annotation.cardinality.has_row_type();
+
+        // Row interface (only for queries that return rows)
+        if has_row_type {
+            out.push('\n');
+            out.push_str(&format!("export interface {} {{\n", row_interface));
+            for field in &query.output_fields {
+                let ts_type = typescript_type(field.sql_type.as_deref(), field.nullable, client);
+                out.push_str(&format!("  {}: {};\n", property_name(&field.name), ts_type));
+            }
+            out.push_str("}\n");
+        }
+
+        // Parameter interface
+        if !query.input_fields.is_empty() {
+            out.push('\n');
+            out.push_str(&format!("export interface {} {{\n", params_interface));
+            for field in &query.input_fields {
+                let mut ts_type =
+                    typescript_type(field.sql_type.as_deref(), field.nullable, client);
+                if field.slice.is_some() {
+                    ts_type = array_type(&ts_type);
+                }
+                out.push_str(&format!("  {}: {};\n", property_name(&field.name), ts_type));
+            }
+            out.push_str("}\n");
+        }
+
+        let return_type = match &query.annotation.cardinality {
+            QueryCardinality::One => format!("Promise<{} | null>", row_interface),
+            QueryCardinality::ExactlyOne => format!("Promise<{}>", row_interface),
+            QueryCardinality::Val => format!("Promise<{}>", value_type(query, client, true)),
+            QueryCardinality::Many => format!("Promise<{}[]>", row_interface),
+            QueryCardinality::Column => {
+                format!("Promise<{}>", array_type(&value_type(query, client, false)))
+            }
+            QueryCardinality::Iter => format!("AsyncGenerator<{}>", row_interface),
+            QueryCardinality::Exec => "Promise<void>".to_string(),
+            QueryCardinality::ExecRows => "Promise<number>".to_string(),
+            QueryCardinality::ExecLastId => "Promise<number | bigint>".to_string(),
+        };
+
+        let mut params = vec![format!("client: {}", client_type)];
+        if !query.input_fields.is_empty() {
+            params.push(format!("params: {}", params_interface));
+        }
+        let is_iter = matches!(query.annotation.cardinality, QueryCardinality::Iter);
+        if is_iter && client == TypeScriptClient::Pg {
+            params.push("batchSize = 1000".to_string());
+        }
+
+        out.push('\n');
+        if is_iter && client == TypeScriptClient::Pg {
+            out.push_str(
+                "// Cursors only live inside a transaction: pass a client that has run BEGIN.\n",
+            );
+        }
+        out.push_str(&format!(
+            "export async function{} {}({}): {} {{\n",
+            if is_iter { "*" } else { "" },
+            fn_name,
+            params.join(", "),
+            return_type
+        ));
+
+        let values: Vec<String> = rewritten
+            .args
+            .iter()
+            .map(|name| {
+                let is_slice = query
+                    .input_fields
+                    .iter()
+                    .any(|f| &f.name == name && is_expanded_slice(f));
+                if is_slice {
+                    format!("...{}", property_access("params", name))
+                } else {
+                    property_access("params", name)
+                }
+            })
+            .collect();
+        let values = values.join(", ");
+
+        // Splice one `?` per value into the SQL for each expanded `@slice`
+        // parameter, or its `empty_slice_sql` for an empty list.
+        let mut sql_ref = const_name.clone();
+        let replacements: Vec<String> = query
+            .input_fields
+            .iter()
+            .filter(|f| is_expanded_slice(f))
+            .map(|field| {
+                format!(
+                    ".replaceAll(\"{}\", {}.map(() => \"?\").join(\", \") || \"{}\")",
+                    slice_marker(field, "?"),
+                    property_access("params", &field.name),
+                    empty_slice_sql(field)
+                )
+            })
+            .collect();
+        if !replacements.is_empty() {
+            out.push_str(&format!(
+                "  const sql = {}{};\n",
+                const_name,
+                replacements.concat()
+            ));
+            sql_ref = "sql".to_string();
+        }
+
+        match client {
+            TypeScriptClient::Pg => {
+                let call = if values.is_empty() {
+                    format!(
+                        "client.query({{ text: {}, rowMode: \"array\" }})",
+                        const_name
+                    )
+                } else {
+                    format!(
+                        "client.query({{ text: {}, values: [{}], rowMode: \"array\" }})",
+                        const_name, values
+                    )
+                };
+                match &query.annotation.cardinality {
+                    QueryCardinality::Exec => {
+                        out.push_str(&format!("  await {};\n", call));
+                    }
+                    QueryCardinality::ExecRows => {
+                        out.push_str(&format!("  const result = await {};\n", call));
+                        out.push_str("  return result.rowCount ?? 0;\n");
+                    }
+                    QueryCardinality::ExecLastId => unreachable!("rejected above"),
+                    QueryCardinality::One => {
+                        out.push_str(&format!("  const result = await {};\n", call));
+                        out.push_str("  const row = result.rows[0];\n");
+                        push_one_row_return(&mut out, query, client);
+                    }
+                    QueryCardinality::Val => {
+                        out.push_str(&format!("  const result = await {};\n", call));
+                        out.push_str("  const row = result.rows[0];\n");
+                        push_val_return(&mut out, query, client);
+                    }
+                    QueryCardinality::Column => {
+                        out.push_str(&format!("  const result = await {};\n", call));
+                        push_column_return(&mut out, query, client, "result.rows");
+                    }
+                    QueryCardinality::ExactlyOne => {
+                        out.push_str(&format!("  const result = await {};\n", call));
+                        push_exactly_one_row_return(
+                            &mut out,
+                            query,
+                            client,
+                            &fn_name,
+                            "result.rows",
+                        );
+                    }
+                    QueryCardinality::Many => {
+                        out.push_str(&format!("  const result = await {};\n", call));
+                        push_many_rows_return(&mut out, query, client, "result.rows");
+                    }
+                    QueryCardinality::Iter => {
+                        out.push_str("  const cursor = `cursor_${++cursorCount}`;\n");
+                        let declare = format!(
+                            "`DECLARE ${{cursor}} NO SCROLL CURSOR FOR ${{{}}}`",
+                            const_name
+                        );
+                        if values.is_empty() {
+                            out.push_str(&format!(
+                                "  await client.query({{ text: {}, rowMode: \"array\" }});\n",
+                                declare
+                            ));
+                        } else {
+                            out.push_str(&format!(
+                                "  await client.query({{ text: {}, values: [{}], rowMode: \"array\" }});\n",
+                                declare, values
+                            ));
+                        }
+                        out.push_str("  try {\n");
+                        out.push_str("    while (true) {\n");
+                        out.push_str(
+                            "      const result = await client.query({ text: `FETCH ${batchSize} FROM ${cursor}`, rowMode: \"array\" });\n",
+                        );
+                        out.push_str("      if (result.rows.length === 0) {\n");
+                        out.push_str("        return;\n");
+                        out.push_str("      }\n");
+                        out.push_str("      for (const row of result.rows) {\n");
+                        out.push_str("        yield {\n");
+                        push_row_object(&mut out, query, client, "        ");
+                        out.push_str("        };\n");
+                        out.push_str("      }\n");
+                        out.push_str("    }\n");
+                        out.push_str("  } finally {\n");
+                        out.push_str(
+                            "    await client.query({ text: `CLOSE ${cursor}`, rowMode: \"array\" });\n",
+                        );
+                        out.push_str("  }\n");
+                    }
+                }
+            }
+            TypeScriptClient::BetterSqlite3 => match &query.annotation.cardinality {
+                QueryCardinality::Exec => {
+                    out.push_str(&format!("  client.prepare({}).run({});\n", sql_ref, values));
+                }
+                QueryCardinality::ExecRows => {
+                    out.push_str(&format!(
+                        "  return client.prepare({}).run({}).changes;\n",
+                        sql_ref, values
+                    ));
+                }
+                QueryCardinality::ExecLastId => {
+                    out.push_str(&format!(
+                        "  return client.prepare({}).run({}).lastInsertRowid;\n",
+                        sql_ref, values
+                    ));
+                }
+                QueryCardinality::One => {
+                    out.push_str(&format!(
+                        "  const row = client.prepare({}).raw(true).get({}) as unknown[] | undefined;\n",
+                        sql_ref, values
+                    ));
+                    push_one_row_return(&mut out, query, client);
+                }
+                QueryCardinality::Val => {
+                    out.push_str(&format!(
+                        "  const row = client.prepare({}).raw(true).get({}) as unknown[] | undefined;\n",
+                        sql_ref, values
+                    ));
+                    push_val_return(&mut out, query, client);
+                }
+                QueryCardinality::Column => {
+                    out.push_str(&format!(
+                        "  const rows = client.prepare({}).raw(true).all({}) as unknown[][];\n",
+                        sql_ref, values
+                    ));
+                    push_column_return(&mut out, query, client, "rows");
+                }
+                QueryCardinality::ExactlyOne => {
+                    out.push_str(&format!(
+                        "  const rows = client.prepare({}).raw(true).all({}) as unknown[][];\n",
+                        sql_ref, values
+                    ));
+                    push_exactly_one_row_return(&mut out, query, client, &fn_name, "rows");
+                }
+                QueryCardinality::Many => {
+                    out.push_str(&format!(
+                        "  const rows = client.prepare({}).raw(true).all({}) as unknown[][];\n",
+                        sql_ref, values
+                    ));
+                    push_many_rows_return(&mut out, query, client, "rows");
+                }
+                QueryCardinality::Iter => {
+                    // better-sqlite3 steps the statement as the iterator advances.
+                    out.push_str(&format!(
+                        "  const rows = client.prepare({}).raw(true).iterate({}) as IterableIterator<unknown[]>;\n",
+                        sql_ref, values
+                    ));
+                    out.push_str("  for (const row of rows) {\n");
+                    out.push_str("    yield {\n");
+                    push_row_object(&mut out, query, client, "    ");
+                    out.push_str("    };\n");
+                    out.push_str("  }\n");
+                }
+            },
+        }
+        out.push_str("}\n");
+    }
+
+    Ok(out)
+}
+
+/// Emit the `{ field: row[i] as T, ... }` object literal for a row.
+fn push_row_object(
+    out: &mut String,
+    query: &QueryParseResult,
+    client: TypeScriptClient,
+    indent: &str,
+) {
+    for (i, field) in query.output_fields.iter().enumerate() {
+        let ts_type = typescript_type(field.sql_type.as_deref(), field.nullable, client);
+        out.push_str(&format!(
+            "{}  {}: row[{}] as {},\n",
+            indent,
+            property_name(&field.name),
+            i,
+            ts_type
+        ));
+    }
+}
+
+fn push_one_row_return(out: &mut String, query: &QueryParseResult, client: TypeScriptClient) {
+    out.push_str("  if (row === undefined) {\n");
+    out.push_str("    return null;\n");
+    out.push_str("  }\n");
+    out.push_str("  return {\n");
+    push_row_object(out, query, client, "  ");
+    out.push_str("  };\n");
+}
+
+/// `T[]`, parenthesising a union element type.
+fn array_type(ts_type: &str) -> String {
+    if ts_type.contains(' ') {
+        format!("({})[]", ts_type)
+    } else {
+        format!("{}[]", ts_type)
+    }
+}
+
+/// The TypeScript type of the single column a `:val` or `:column` query
+/// returns. `:val` passes `nullable` so a missing row can be `null`.
+fn value_type(query: &QueryParseResult, client: TypeScriptClient, nullable: bool) -> String {
+    let field = &query.output_fields[0];
+    typescript_type(
+        field.sql_type.as_deref(),
+        field.nullable || nullable,
+        client,
+    )
+}
+
+fn push_val_return(out: &mut String, query: &QueryParseResult, client: TypeScriptClient) {
+    out.push_str("  if (row === undefined) {\n");
+    out.push_str("    return null;\n");
+    out.push_str("  }\n");
+    out.push_str(&format!(
+        "  return row[0] as {};\n",
+        value_type(query, client, false)
+    ));
+}
+
+fn push_column_return(
+    out: &mut String,
+    query: &QueryParseResult,
+    client: TypeScriptClient,
+    rows: &str,
+) {
+    out.push_str(&format!(
+        "  return {}.map((row) => row[0] as {});\n",
+        rows,
+        value_type(query, client, false)
+    ));
+}
+
+fn push_exactly_one_row_return(
+    out: &mut String,
+    query: &QueryParseResult,
+    client: TypeScriptClient,
+    fn_name: &str,
+    rows: &str,
+) {
+    out.push_str(&format!("  if ({}.length === 0) {{\n", rows));
+    out.push_str(&format!("    throw new NoRowsError(\"{}\");\n", fn_name));
+    out.push_str("  }\n");
+    out.push_str(&format!("  if ({}.length > 1) {{\n", rows));
+    out.push_str(&format!(
+        "    throw new TooManyRowsError(\"{}\");\n",
+        fn_name
+    ));
+    out.push_str("  }\n");
+    out.push_str(&format!("  const row = {}[0];\n", rows));
+    out.push_str("  return {\n");
+    push_row_object(out, query, client, "  ");
+    out.push_str("  };\n");
+}
+
+fn push_many_rows_return(
+    out: &mut String,
+    query: &QueryParseResult,
+    client: TypeScriptClient,
+    rows: &str,
+) {
+    out.push_str(&format!("  return {}.map((row) => ({{\n", rows));
+    push_row_object(out, query, client, "  ");
+    out.push_str("  }));\n");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::codegen::test_support::parse_queries;
+
+    /// One query per cardinality except `:execlastid`, which node-postgres
+    /// doesn't support.
+    const QUERIES_SQL: &str = "-- get_user :one
+             SELECT id, email, bio FROM users WHERE id = :id;
+
+             -- get_user_by_email :one!
+             SELECT id, email FROM users WHERE email = :email;
+
+             -- list_users :many
+             SELECT id, email FROM users ORDER BY id;
+
+             -- get_email :val
+             SELECT email FROM users WHERE id = :id;
+
+             -- list_emails :column
+             SELECT email FROM users ORDER BY id;
+
+             -- iter_users_after :iter
+             SELECT id, email FROM users WHERE id > :after_id ORDER BY id;
+
+             -- delete_user :exec
+             DELETE FROM users WHERE id = :id;
+
+             -- clear_bio :execrows
+             UPDATE users SET bio = NULL WHERE bio = :bio;";
+
+    fn assert_contains_all(contents: &str, snippets: &[&str]) {
+        for snippet in snippets {
+            assert!(
+                contents.contains(snippet),
+                "missing:\n{}\nin:\n{}",
+                snippet,
+                contents
+            );
+        }
+    }
+
+    #[test]
+    fn test_generate_typescript_file_better_sqlite3() {
+        let sql = format!(
+            "{}\n\n-- create_user :execlastid\nINSERT INTO users (email) VALUES (:email);",
+            QUERIES_SQL
+        );
+        let contents = generate_typescript_file(
+            &parse_queries(&sql),
+            "users.sql",
+            TypeScriptClient::BetterSqlite3,
+        )
+        .unwrap();
+
+        assert_contains_all(
+            &contents,
+            &[
+                r#"
+const getUserSql = `SELECT id, email, bio FROM users WHERE id = ?`;
+
+export interface GetUserRow {
+  id: number;
+  email: string;
+  bio: string | null;
+}
+
+export interface GetUserParams {
+  id: number;
+}
+"#,
+                r#"
+export async function getUser(client: Database, params: GetUserParams): Promise<GetUserRow | null> {
+  const row = client.prepare(getUserSql).raw(true).get(params.id) as unknown[] | undefined;
+  if (row === undefined) {
+    return null;
+  }
+  return {
+    id: row[0] as number,
+    email: row[1] as string,
+    bio: row[2] as string | null,
+  };
+}
+"#,
+                r#"
+export async function getUserByEmail(client: Database, params: GetUserByEmailParams): Promise<GetUserByEmailRow> {
+  const rows = client.prepare(getUserByEmailSql).raw(true).all(params.email) as unknown[][];
+  if (rows.length === 0) {
+    throw new NoRowsError("getUserByEmail");
+  }
+  if (rows.length > 1) {
+    throw new TooManyRowsError("getUserByEmail");
+  }
+  const row = rows[0];
+  return {
+    id: row[0] as number,
+    email: row[1] as string,
+  };
+}
+"#,
+                r#"
+export async function listUsers(client: Database): Promise<ListUsersRow[]> {
+  const rows = client.prepare(listUsersSql).raw(true).all() as unknown[][];
+  return rows.map((row) => ({
+    id: row[0] as number,
+    email: row[1] as string,
+  }));
+}
+"#,
+                r#"
+export async function getEmail(client: Database, params: GetEmailParams): Promise<string | null> {
+  const row = client.prepare(getEmailSql).raw(true).get(params.id) as unknown[] | undefined;
+  if (row === undefined) {
+    return null;
+  }
+  return row[0] as string;
+}
+"#,
+                r#"
+export async function listEmails(client: Database): Promise<string[]> {
+  const rows = client.prepare(listEmailsSql).raw(true).all() as unknown[][];
+  return rows.map((row) => row[0] as string);
+}
+"#,
+                r#"
+export async function* iterUsersAfter(client: Database, params: IterUsersAfterParams): AsyncGenerator<IterUsersAfterRow> {
+  const rows = client.prepare(iterUsersAfterSql).raw(true).iterate(params.after_id) as IterableIterator<unknown[]>;
+  for (const row of rows) {
+    yield {
+      id: row[0] as number,
+      email: row[1] as string,
+    };
+  }
+}
+"#,
+                r#"
+export async function deleteUser(client: Database, params: DeleteUserParams): Promise<void> {
+  client.prepare(deleteUserSql).run(params.id);
+}
+"#,
+                r#"
+export async function clearBio(client: Database, params: ClearBioParams): Promise<number> {
+  return client.prepare(clearBioSql).run(params.bio).changes;
+}
+"#,
+                r#"
+export async function createUser(client: Database, params: CreateUserParams): Promise<number | bigint> {
+  return client.prepare(createUserSql).run(params.email).lastInsertRowid;
+}
+"#,
+            ],
+        );
+    }
+
+    #[test]
+    fn test_generate_typescript_file_pg() {
+        let contents = generate_typescript_file(
+            &parse_queries(QUERIES_SQL),
+            "users.sql",
+            TypeScriptClient::Pg,
+        )
+        .unwrap();
+
+        assert_contains_all(
+            &contents,
+            &[
+                "const getUserSql = `SELECT id, email, bio FROM users WHERE id = $1`;\n",
+                r#"
+export async function getUser(client: Queryable, params: GetUserParams): Promise<GetUserRow | null> {
+  const result = await client.query({ text: getUserSql, values: [params.id], rowMode: "array" });
+  const row = result.rows[0];
+  if (row === undefined) {
+    return null;
+  }
+  return {
+    id: row[0] as number,
+    email: row[1] as string,
+    bio: row[2] as string | null,
+  };
+}
+"#,
+                r#"
+export async function getUserByEmail(client: Queryable, params: GetUserByEmailParams): Promise<GetUserByEmailRow> {
+  const result = await client.query({ text: getUserByEmailSql, values: [params.email], rowMode: "array" });
+  if (result.rows.length === 0) {
+    throw new NoRowsError("getUserByEmail");
+  }
+  if (result.rows.length > 1) {
+    throw new TooManyRowsError("getUserByEmail");
+  }
+  const row = result.rows[0];
+  return {
+    id: row[0] as number,
+    email: row[1] as string,
+  };
+}
+"#,
+                r#"
+export async function listUsers(client: Queryable): Promise<ListUsersRow[]> {
+  const result = await client.query({ text: listUsersSql, rowMode: "array" });
+  return result.rows.map((row) => ({
+    id: row[0] as number,
+    email: row[1] as string,
+  }));
+}
+"#,
+                r#"
+export async function getEmail(client: Queryable, params: GetEmailParams): Promise<string | null> {
+  const result = await client.query({ text: getEmailSql, values: [params.id], rowMode: "array" });
+  const row = result.rows[0];
+  if (row === undefined) {
+    return null;
+  }
+  return row[0] as string;
+}
+"#,
+                r#"
+export async function listEmails(client: Queryable): Promise<string[]> {
+  const result = await client.query({ text: listEmailsSql, rowMode: "array" });
+  return result.rows.map((row) => row[0] as string);
+}
+"#,
+                r#"
+export async function* iterUsersAfter(client: Queryable, params: IterUsersAfterParams, batchSize = 1000): AsyncGenerator<IterUsersAfterRow> {
+  const cursor = `cursor_${++cursorCount}`;
+  await client.query({ text: `DECLARE ${cursor} NO SCROLL CURSOR FOR ${iterUsersAfterSql}`, values: [params.after_id], rowMode: "array" });
+  try {
+    while (true) {
+      const result = await client.query({ text: `FETCH ${batchSize} FROM ${cursor}`, rowMode: "array" });
+      if (result.rows.length === 0) {
+        return;
+      }
+      for (const row of result.rows) {
+        yield {
+          id: row[0] as number,
+          email: row[1] as string,
+        };
+      }
+    }
+  } finally {
+    await client.query({ text: `CLOSE ${cursor}`, rowMode: "array" });
+  }
+}
+"#,
+                r#"
+export async function deleteUser(client: Queryable, params: DeleteUserParams): Promise<void> {
+  await client.query({ text: deleteUserSql, values: [params.id], rowMode: "array" });
+}
+"#,
+                r#"
+export async function clearBio(client: Queryable, params: ClearBioParams): Promise<number> {
+  const result = await client.query({ text: clearBioSql, values: [params.bio], rowMode: "array" });
+  return result.rowCount ?? 0;
+}
+"#,
+            ],
+        );
+
+        let lastid = parse_queries(
+            "-- create_user :execlastid
+             INSERT INTO users (email) VALUES (:email);",
+        );
+        assert!(generate_typescript_file(&lastid, "users.sql", TypeScriptClient::Pg).is_err());
+    }
+
+    #[test]
+    fn test_generate_typescript_file_quotes_property_names() {
+        let queries = parse_queries(
+            "-- get_counts :one
+             SELECT COUNT(*) AS \"user count\", email FROM users GROUP BY email;",
+        );
+        let contents =
+            generate_typescript_file(&queries, "users.sql", TypeScriptClient::Pg).unwrap();
+
+        assert_contains_all(
+            &contents,
+            &[
+                "  \"user count\": string;\n  email: string;\n",
+                "    \"user count\": row[0] as string,\n    email: row[1] as string,\n",
+            ],
+        );
+    }
+}
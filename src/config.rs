@@ -1,7 +1,6 @@
 use clap::Parser;
 use serde::Deserialize;
 use std::path::PathBuf;
-use thiserror;
 
 pub enum SQLDialect {
     Generic,
@@ -28,6 +27,104 @@ impl SQLDialect {
     }
 }
 
+pub enum Language {
+    Python,
+    Rust,
+    TypeScript,
+    Go,
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum LanguageError {
+    #[error("unsupported language: {0}")]
+    Unsupported(String),
+}
+
+impl Language {
+    pub fn from_str(value: &str) -> Result<Language, LanguageError> {
+        match value.to_lowercase().as_str() {
+            "python" => Ok(Language::Python),
+            "rust" => Ok(Language::Rust),
+            "typescript" => Ok(Language::TypeScript),
+            "go" => Ok(Language::Go),
+            _ => Err(LanguageError::Unsupported(String::from(value))),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TypeScriptClient {
+    /// node-postgres (`pg`)
+    Pg,
+    BetterSqlite3,
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum TypeScriptClientError {
+    #[error("unsupported TypeScript client: {0}")]
+    Unsupported(String),
+}
+
+impl TypeScriptClient {
+    pub fn from_str(value: &str) -> Result<TypeScriptClient, TypeScriptClientError> {
+        match value.to_lowercase().as_str() {
+            "pg" => Ok(TypeScriptClient::Pg),
+            "better-sqlite3" => Ok(TypeScriptClient::BetterSqlite3),
+            _ => Err(TypeScriptClientError::Unsupported(String::from(value))),
+        }
+    }
+}
+
+/// A DB-API 2.0 `paramstyle`, plus `dollar` for `$1` markers.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ParamStyle {
+    /// `?`
+    Qmark,
+    /// `:1`
+    Numeric,
+    /// `:name`
+    Named,
+    /// `%s`
+    Format,
+    /// `%(name)s`
+    Pyformat,
+    /// `$1`
+    Dollar,
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum ParamStyleError {
+    #[error("unsupported paramstyle: {0}")]
+    Unsupported(String),
+    #[error("paramstyle \"{0}\" is not supported by the {1} backend")]
+    UnsupportedByBackend(&'static str, &'static str),
+}
+
+impl ParamStyle {
+    pub fn from_str(value: &str) -> Result<ParamStyle, ParamStyleError> {
+        match value.to_lowercase().as_str() {
+            "qmark" => Ok(ParamStyle::Qmark),
+            "numeric" => Ok(ParamStyle::Numeric),
+            "named" => Ok(ParamStyle::Named),
+            "format" => Ok(ParamStyle::Format),
+            "pyformat" => Ok(ParamStyle::Pyformat),
+            "dollar" => Ok(ParamStyle::Dollar),
+            _ => Err(ParamStyleError::Unsupported(String::from(value))),
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ParamStyle::Qmark => "qmark",
+            ParamStyle::Numeric => "numeric",
+            ParamStyle::Named => "named",
+            ParamStyle::Format => "format",
+            ParamStyle::Pyformat => "pyformat",
+            ParamStyle::Dollar => "dollar",
+        }
+    }
+}
+
 #[derive(Parser)]
 pub struct Args {
     pub project_path: Option<PathBuf>,
@@ -37,14 +134,42 @@ pub struct Args {
 pub struct GenerateConfig {
     pub dialect: Option<String>,
 
+    pub language: Option<String>,
+
+    /// Executable to delegate code generation to instead of a built-in
+    /// `language` backend. Paths with a directory are relative to the project.
+    pub plugin: Option<PathBuf>,
+
     #[serde(rename = "queries-dir")]
     pub queries_dir: Option<PathBuf>,
 
     #[serde(rename = "schema-file")]
     pub schema_file: Option<PathBuf>,
 
+    /// Directory of migration files. Every `.sql` file is applied in lexical
+    /// order of file name, replacing `schema-file`.
+    #[serde(rename = "schema-dir")]
+    pub schema_dir: Option<PathBuf>,
+
     #[serde(rename = "output-dir")]
     pub output_dir: Option<PathBuf>,
+
+    /// Database client targeted by the TypeScript backend: `pg` or
+    /// `better-sqlite3`. Defaults to `better-sqlite3` for the SQLite dialect
+    /// and `pg` otherwise.
+    #[serde(rename = "typescript-client")]
+    pub typescript_client: Option<String>,
+
+    /// Package name for the Go backend. Defaults to `queries`.
+    #[serde(rename = "go-package")]
+    pub go_package: Option<String>,
+
+    /// Placeholder style of the driver: `qmark`, `numeric`, `named`,
+    /// `format`, `pyformat` or `dollar`. Python accepts every style; Rust and
+    /// Go accept `qmark` and `dollar`; TypeScript only its client's own style
+    /// (`dollar` for `pg`, `qmark` for `better-sqlite3`). Plugins receive it in
+    /// the request. Without it each backend keeps its default.
+    pub paramstyle: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -2,21 +2,27 @@ use clap::Parser;
 use sqlparser::dialect;
 use sqlparser::parser::Parser as SQLParser;
 use std::fs;
-use std::path::PathBuf;
-use toml;
+use std::path::{Path, PathBuf};
 
-mod codegen {
-    pub mod python;
-}
+mod codegen;
 mod config;
 mod query;
 mod schema;
 mod util;
 
-use codegen::python::generate_python_file;
-use config::{Args, Config, SQLDialect};
-use query::{extract_query_annotations, process_sql_statement};
-use schema::parse_schema_file;
+use codegen::go::GoCodegen;
+use codegen::placeholders::PositionalStyle;
+use codegen::plugin::PluginCodegen;
+use codegen::python::PythonCodegen;
+use codegen::rust::RustCodegen;
+use codegen::typescript::TypeScriptCodegen;
+use codegen::{Codegen, GeneratedFile};
+use config::{Args, Config, Language, ParamStyle, ParamStyleError, SQLDialect, TypeScriptClient};
+use query::{
+    bind_slices_as_arrays, check_dialect_placeholders, extract_query_annotations,
+    process_sql_statement,
+};
+use schema::{SchemaError, SchemaParseResult};
 use util::format_sql_parser_error;
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
@@ -38,6 +44,96 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     println!("Using SQL dialect: {}", selected_dialect);
 
+    let selected_language = config
+        .generate
+        .language
+        .unwrap_or_else(|| "python".to_string());
+    let language = Language::from_str(&selected_language)?;
+
+    let paramstyle = config
+        .generate
+        .paramstyle
+        .as_deref()
+        .map(ParamStyle::from_str)
+        .transpose()?;
+    // Backends that bind positionally only support the two positional styles.
+    let positional_style = |backend: &'static str| match paramstyle {
+        None => Ok(None),
+        Some(ParamStyle::Qmark) => Ok(Some(PositionalStyle::QuestionMark)),
+        Some(ParamStyle::Dollar) => Ok(Some(PositionalStyle::Dollar)),
+        Some(style) => Err(ParamStyleError::UnsupportedByBackend(
+            style.as_str(),
+            backend,
+        )),
+    };
+
+    let backend: Box<dyn Codegen> = match &config.generate.plugin {
+        Some(plugin) => {
+            // A bare command name is looked up on PATH
+            let command = if plugin.components().count() > 1 {
+                project_path.join(plugin)
+            } else {
+                plugin.clone()
+            };
+            println!("Generating code with plugin: {}", command.display());
+            Box::new(PluginCodegen {
+                command,
+                dialect: selected_dialect.to_lowercase(),
+                paramstyle,
+            })
+        }
+        None => {
+            println!("Generating code for: {}", selected_language);
+            match language {
+                Language::Python => Box::new(PythonCodegen { paramstyle }),
+                Language::Rust => Box::new(RustCodegen {
+                    style: positional_style("Rust")?,
+                }),
+                Language::TypeScript => {
+                    let client = match &config.generate.typescript_client {
+                        Some(client) => TypeScriptClient::from_str(client)?,
+                        None => match sql_dialect {
+                            SQLDialect::SQLite => TypeScriptClient::BetterSqlite3,
+                            _ => TypeScriptClient::Pg,
+                        },
+                    };
+                    // Each client only accepts its driver's own style.
+                    match (client, paramstyle) {
+                        (_, None)
+                        | (TypeScriptClient::Pg, Some(ParamStyle::Dollar))
+                        | (TypeScriptClient::BetterSqlite3, Some(ParamStyle::Qmark)) => {}
+                        (TypeScriptClient::Pg, Some(style)) => {
+                            return Err(ParamStyleError::UnsupportedByBackend(
+                                style.as_str(),
+                                "TypeScript pg",
+                            )
+                            .into());
+                        }
+                        (TypeScriptClient::BetterSqlite3, Some(style)) => {
+                            return Err(ParamStyleError::UnsupportedByBackend(
+                                style.as_str(),
+                                "TypeScript better-sqlite3",
+                            )
+                            .into());
+                        }
+                    }
+                    Box::new(TypeScriptCodegen { client })
+                }
+                Language::Go => Box::new(GoCodegen {
+                    package: config
+                        .generate
+                        .go_package
+                        .unwrap_or_else(|| "queries".to_string()),
+                    style: positional_style("Go")?.unwrap_or(match sql_dialect {
+                        SQLDialect::PostgreSQL => PositionalStyle::Dollar,
+                        _ => PositionalStyle::QuestionMark,
+                    }),
+                    last_insert_id: !matches!(sql_dialect, SQLDialect::PostgreSQL),
+                }),
+            }
+        }
+    };
+
     let parser_dialect: &dyn dialect::Dialect = match sql_dialect {
         SQLDialect::Generic => &dialect::GenericDialect {},
         SQLDialect::SQLite => &dialect::SQLiteDialect {},
@@ -45,13 +141,6 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         SQLDialect::MySQL => &dialect::MySqlDialect {},
     };
 
-    let schema_file_path = project_path.join(
-        config
-            .generate
-            .schema_file
-            .unwrap_or_else(|| PathBuf::from("schema.sql")),
-    );
-
     let queries_dir_path = project_path.join(
         config
             .generate
@@ -77,32 +166,67 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         std::process::exit(1);
     }
 
-    println!("Reading schema file: {}", schema_file_path.display());
-
-    let schema_sql = match fs::read_to_string(&schema_file_path) {
-        Err(err) => {
-            eprintln!(
-                "Failed to read schema file \"{}\": {}",
-                schema_file_path.display(),
-                err
-            );
-            std::process::exit(1);
+    let schema_file_paths = match &config.generate.schema_dir {
+        Some(schema_dir) => {
+            if config.generate.schema_file.is_some() {
+                eprintln!("Set either \"schema-file\" or \"schema-dir\", not both");
+                std::process::exit(1);
+            }
+            let schema_dir_path = project_path.join(schema_dir);
+            println!("Reading schema directory: {}", schema_dir_path.display());
+            match list_sql_files(&schema_dir_path) {
+                Err(err) => {
+                    eprintln!(
+                        "Failed to read schema directory \"{}\": {}",
+                        schema_dir_path.display(),
+                        err
+                    );
+                    std::process::exit(1);
+                }
+                Ok(paths) => paths,
+            }
         }
-        Ok(contents) => contents,
+        None => vec![project_path.join(
+            config
+                .generate
+                .schema_file
+                .clone()
+                .unwrap_or_else(|| PathBuf::from("schema.sql")),
+        )],
     };
 
-    let schema = match parse_schema_file(&schema_sql, parser_dialect) {
-        Err(err) => {
+    let mut schema = SchemaParseResult::default();
+
+    for schema_file_path in &schema_file_paths {
+        println!("Reading schema file: {}", schema_file_path.display());
+
+        let schema_sql = match fs::read_to_string(schema_file_path) {
+            Err(err) => {
+                eprintln!(
+                    "Failed to read schema file \"{}\": {}",
+                    schema_file_path.display(),
+                    err
+                );
+                std::process::exit(1);
+            }
+            Ok(contents) => contents,
+        };
+
+        if let Err(err) = schema.apply_sql(&schema_sql, parser_dialect) {
+            let message = match &err {
+                SchemaError::Parse(err) => format_sql_parser_error(err, &schema_sql),
+                err => err.to_string(),
+            };
             eprintln!(
                 "Failed to parse schema file \"{}\": {}",
                 schema_file_path.display(),
-                err
+                message
             );
             std::process::exit(1);
         }
-        Ok(result) => result,
-    };
+    }
 
+    let mut generated_any = false;
     for entry in fs::read_dir(&queries_dir_path)? {
         match entry {
             Ok(dir_entry) => {
@@ -135,7 +259,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                         let annotations = extract_query_annotations(&sql);
                         let mut file_queries = Vec::new();
 
-                        for (statement, annotation) in ast.iter().zip(annotations.into_iter()) {
+                        for (statement, annotation) in ast.iter().zip(annotations) {
                             let annotation = match annotation {
                                 Some(a) => a,
                                 None => {
@@ -148,15 +272,37 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                                 }
                             };
 
+                            if let Err(reason) = backend.validate_query_name(&annotation.name) {
+                                eprintln!(
+                                    "Warning: annotation name {:?} in \"{}\" {}, skipping",
+                                    annotation.name,
+                                    path.display(),
+                                    reason,
+                                );
+                                continue;
+                            }
+
                             eprintln!("  query {:?} ({})", annotation.name, annotation.cardinality,);
 
-                            match process_sql_statement(statement, annotation, &schema) {
+                            let processed = check_dialect_placeholders(statement, &sql_dialect)
+                                .and_then(|()| {
+                                    process_sql_statement(statement, annotation, &schema)
+                                })
+                                .and_then(|mut result| {
+                                    // PostgreSQL binds a list as one array, so `@slice`
+                                    // parameters needn't be expanded at call time.
+                                    if matches!(sql_dialect, SQLDialect::PostgreSQL) {
+                                        bind_slices_as_arrays(&mut result)?;
+                                    }
+                                    Ok(result)
+                                });
+                            match processed {
                                 Ok(result) => file_queries.push(result),
                                 Err(err) => {
                                     eprintln!(
-                                        "Failed to process SQL statement \"{}\": {:?}",
+                                        "Failed to process SQL statement \"{}\": {}",
                                         path.display(),
-                                        err,
+                                        err.format(statement, &sql),
                                     );
                                 }
                             }
@@ -182,11 +328,21 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                     .file_name()
                     .and_then(|s| s.to_str())
                     .unwrap_or("unknown.sql");
-                let output_path = output_dir_path.join(format!("{}.py", stem));
+                let files = match backend.generate(&queries, &schema, source_filename, stem) {
+                    Err(err) => {
+                        eprintln!(
+                            "Failed to generate code for \"{}\": {}",
+                            path.display(),
+                            err
+                        );
+                        continue;
+                    }
+                    Ok(files) => files,
+                };
+                generated_any = true;
 
-                match generate_python_file(&queries, source_filename, &output_path) {
-                    Ok(()) => println!("  wrote {}", output_path.display()),
-                    Err(err) => eprintln!("Failed to write \"{}\": {}", output_path.display(), err),
+                for file in files {
+                    write_generated_file(&output_dir_path, file);
                 }
             }
             Err(err) => {
@@ -199,5 +355,39 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         }
     }
 
+    if generated_any {
+        for file in backend.shared_files() {
+            write_generated_file(&output_dir_path, file);
+        }
+    }
+
     Ok(())
 }
+
+/// Write `file` below the output directory, reporting the outcome.
+fn write_generated_file(output_dir_path: &Path, file: GeneratedFile) {
+    let output_path = output_dir_path.join(&file.path);
+    // Plugins may place files in subdirectories.
+    let written = match output_path.parent() {
+        Some(parent) => fs::create_dir_all(parent),
+        None => Ok(()),
+    }
+    .and_then(|()| fs::write(&output_path, file.contents));
+    match written {
+        Ok(()) => println!("  wrote {}", output_path.display()),
+        Err(err) => eprintln!("Failed to write \"{}\": {}", output_path.display(), err),
+    }
+}
+
+/// The `.sql` files directly inside `dir`, sorted by file name.
+fn list_sql_files(dir: &Path) -> Result<Vec<PathBuf>, std::io::Error> {
+    let mut paths: Vec<PathBuf> = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_file() && path.extension().and_then(|e| e.to_str()) == Some("sql") {
+            paths.push(path);
+        }
+    }
+    paths.sort_by(|a, b| a.file_name().cmp(&b.file_name()));
+    Ok(paths)
+}
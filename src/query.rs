@@ -1,8 +1,18 @@
-use crate::schema::{FieldSource, SchemaParseResult};
+use crate::config::SQLDialect;
+use crate::schema::{
+    object_name_key, unknown_column_type, Column, FieldSource, SchemaParseResult, Table, TableKind,
+};
 use sqlparser::ast::{
-    Expr, FromTable, JoinConstraint, SelectItem, SetExpr, Statement, TableFactor, Value,
+    visit_expressions, visit_expressions_mut, Assignment, AssignmentTarget, BinaryOperator,
+    CastKind, Cte, Expr, FromTable, FunctionArg, FunctionArgExpr, FunctionArguments, Ident,
+    JoinOperator, LimitClause, OnConflict, OnConflictAction, OnInsert, Query, Select, SelectItem,
+    SelectItemQualifiedWildcardKind, SetExpr, SetOperator, Statement, TableFactor, TableObject,
+    TableWithJoins, UnaryOperator, UpdateTableFromKind, Value, Visit, Visitor,
+    WildcardAdditionalOptions,
 };
+use sqlparser::tokenizer::Location;
 use std::collections::HashMap;
+use std::ops::ControlFlow;
 
 // ---------------------------------------------------------------------------
 // Annotation types
@@ -11,16 +21,55 @@ use std::collections::HashMap;
 #[derive(Clone, Debug)]
 pub enum QueryCardinality {
     One,
+    /// `:one!`: exactly one row, with no row or a second row being an error.
+    ExactlyOne,
     Many,
+    /// `:val`: the single column of the first row, if any.
+    Val,
+    /// `:column`: the single column of every row.
+    Column,
+    /// Stream the rows lazily instead of fetching them all up front.
+    Iter,
     Exec,
+    /// Execute and return the number of affected rows.
+    ExecRows,
+    /// Execute and return the id of the last inserted row.
+    ExecLastId,
+}
+
+impl QueryCardinality {
+    /// True for the cardinalities that don't return rows.
+    pub fn is_exec(&self) -> bool {
+        matches!(
+            self,
+            QueryCardinality::Exec | QueryCardinality::ExecRows | QueryCardinality::ExecLastId
+        )
+    }
+
+    /// True for the cardinalities that return a single column's values
+    /// rather than rows.
+    pub fn is_scalar(&self) -> bool {
+        matches!(self, QueryCardinality::Val | QueryCardinality::Column)
+    }
+
+    /// True for the cardinalities whose functions return row objects.
+    pub fn has_row_type(&self) -> bool {
+        !self.is_exec() && !self.is_scalar()
+    }
 }
 
 impl std::fmt::Display for QueryCardinality {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             QueryCardinality::One => write!(f, ":one"),
+            QueryCardinality::ExactlyOne => write!(f, ":one!"),
             QueryCardinality::Many => write!(f, ":many"),
+            QueryCardinality::Val => write!(f, ":val"),
+            QueryCardinality::Column => write!(f, ":column"),
+            QueryCardinality::Iter => write!(f, ":iter"),
             QueryCardinality::Exec => write!(f, ":exec"),
+            QueryCardinality::ExecRows => write!(f, ":execrows"),
+            QueryCardinality::ExecLastId => write!(f, ":execlastid"),
         }
     }
 }
@@ -29,6 +78,25 @@ impl std::fmt::Display for QueryCardinality {
 pub struct QueryAnnotation {
     pub name: String,
     pub cardinality: QueryCardinality,
+    /// `-- @param` and `-- @slice` directives from the annotation's comment
+    /// block.
+    pub params: Vec<ParamDirective>,
+}
+
+/// A `-- @param name [as new_name] [type][?]` directive, which names or types
+/// one of the query's parameters.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ParamDirective {
+    /// The parameter as the query refers to it: `cid`, `:cid`, `p1` or `$1`.
+    pub name: String,
+    /// The name to give the parameter in generated code.
+    pub rename: Option<String>,
+    /// A SQL type, or a Python type such as `datetime.datetime`.
+    pub data_type: Option<String>,
+    /// True when the directive ends with `?`.
+    pub nullable: bool,
+    /// True for `@slice`, which makes the parameter a list of values.
+    pub slice: bool,
 }
 
 // ---------------------------------------------------------------------------
@@ -49,10 +117,32 @@ pub enum PlaceholderKind {
 #[derive(Debug)]
 pub struct QueryInputField {
     pub name: String,
+    /// The Python type of the parameter (`Any` when it can't be inferred).
     pub data_type: String,
+    /// The SQL data type of the column the parameter binds to, if known.
+    pub sql_type: Option<String>,
+    /// True when the parameter binds to a nullable column.
+    pub nullable: bool,
     /// The style of the original SQL placeholder, used by codegen to decide
     /// whether to emit a named dict or a positional tuple.
     pub placeholder_kind: PlaceholderKind,
+    /// The name the placeholder binds under: `name` for `:name`, and `pN`
+    /// for `$N` and the N-th `?`. Unlike `name`, an `@param` directive can't
+    /// change it.
+    pub placeholder_name: String,
+    /// Set for `@slice` parameters, which take a list of values.
+    pub slice: Option<SliceBinding>,
+}
+
+/// How the values of a `@slice` parameter are bound.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SliceBinding {
+    /// The placeholder is expanded into one bind marker per value when the
+    /// query runs. `negated` is set when the list is a `NOT IN (...)` list,
+    /// which an empty list must leave matching every row.
+    Expanded { negated: bool },
+    /// The list is bound as a single array, as in PostgreSQL's `= ANY($1)`.
+    Array,
 }
 
 #[derive(Debug)]
@@ -67,6 +157,25 @@ pub struct QueryOutputFieldSource {
 pub struct QueryOutputField {
     pub source: QueryOutputFieldSource,
     pub name: String,
+    /// The SQL data type of the source column, once resolved against the schema.
+    pub sql_type: Option<String>,
+    /// The Python type mapped from `sql_type` (`Any` when unresolved).
+    pub python_type: String,
+    /// True when the column is nullable or comes from the optional side of an
+    /// outer join.
+    pub nullable: bool,
+}
+
+impl QueryOutputField {
+    fn unresolved(source: QueryOutputFieldSource, name: String) -> QueryOutputField {
+        QueryOutputField {
+            source,
+            name,
+            sql_type: None,
+            python_type: "Any".to_string(),
+            nullable: false,
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -93,6 +202,34 @@ pub enum QueryError {
     UnsupportedExpression {
         description: String,
     },
+    ColumnCountMismatch {
+        relation: String,
+        expected: usize,
+        found: usize,
+    },
+    IncompatibleSetOperation {
+        operator: String,
+        reason: String,
+    },
+    InvalidParamDirective {
+        name: String,
+        reason: String,
+    },
+    /// A `:val` or `:column` query that doesn't project exactly one column.
+    ScalarColumnCount {
+        cardinality: String,
+        found: usize,
+    },
+    /// Placeholders of more than one [`PlaceholderKind`] in one statement.
+    MixedPlaceholders {
+        placeholders: Vec<(Location, String)>,
+    },
+    /// A placeholder the dialect doesn't support.
+    UnsupportedPlaceholder {
+        placeholder: String,
+        location: Location,
+        dialect: String,
+    },
 }
 
 impl QueryError {
@@ -128,33 +265,68 @@ impl QueryError {
                     description, statement
                 )
             }
+            QueryError::ColumnCountMismatch {
+                relation,
+                expected,
+                found,
+            } => format!(
+                "Column count mismatch: \"{}\" has {} columns but its query produces {} \
+                 (query: {})",
+                relation, expected, found, statement
+            ),
+            QueryError::IncompatibleSetOperation { operator, reason } => format!(
+                "Incompatible {} branches: {} (query: {})",
+                operator, reason, statement
+            ),
+            QueryError::InvalidParamDirective { name, reason } => format!(
+                "Invalid @param directive for \"{}\": {} (query: {})",
+                name, reason, statement
+            ),
+            QueryError::ScalarColumnCount { cardinality, found } => format!(
+                "{} queries must return exactly one column but this one returns {} \
+                 (query: {})",
+                cardinality, found, statement
+            ),
+            QueryError::MixedPlaceholders { placeholders } => {
+                let listed: Vec<String> = placeholders
+                    .iter()
+                    .map(|(location, placeholder)| {
+                        format!("{} at {}:{}", placeholder, location.line, location.column)
+                    })
+                    .collect();
+                format!(
+                    "Mixed placeholder styles: {}; use one style per query (query: {})",
+                    listed.join(", "),
+                    statement
+                )
+            }
+            QueryError::UnsupportedPlaceholder {
+                placeholder,
+                location,
+                dialect,
+            } => format!(
+                "Unsupported placeholder: {} at {}:{} is not valid in {} (query: {})",
+                placeholder, location.line, location.column, dialect, statement
+            ),
         }
     }
 }
 
-// ---------------------------------------------------------------------------
-// Python keyword set (used for annotation name validation)
-// ---------------------------------------------------------------------------
-
-const PYTHON_KEYWORDS: &[&str] = &[
-    "False", "None", "True", "and", "as", "assert", "async", "await", "break", "class", "continue",
-    "def", "del", "elif", "else", "except", "finally", "for", "from", "global", "if", "import",
-    "in", "is", "lambda", "nonlocal", "not", "or", "pass", "raise", "return", "try", "while",
-    "with", "yield",
-];
-
-fn is_python_keyword(name: &str) -> bool {
-    PYTHON_KEYWORDS.contains(&name)
-}
-
 // ---------------------------------------------------------------------------
 // Annotation pre-pass
 // ---------------------------------------------------------------------------
 
+/// Pair each statement in `sql` with its `-- name :cardinality` annotation.
+/// `-- @param` lines in the comments before the statement are attached to
+/// the annotation.
+///
+/// Names are taken as written; whether they are usable in the target language
+/// is up to the codegen backend.
 pub fn extract_query_annotations(sql: &str) -> Vec<Option<QueryAnnotation>> {
     let mut annotations: Vec<Option<QueryAnnotation>> = Vec::new();
 
     let mut pending_annotation: Option<QueryAnnotation> = None;
+    let mut pending_params: Vec<ParamDirective> = Vec::new();
     let mut in_statement = false;
 
     for line in sql.lines() {
@@ -166,6 +338,24 @@ pub fn extract_query_annotations(sql: &str) -> Vec<Option<QueryAnnotation>> {
 
         if let Some(rest) = trimmed.strip_prefix("--") {
             let comment = rest.trim();
+
+            let directive = match comment.strip_prefix("@param") {
+                Some(directive) => Some((directive, false)),
+                None => comment
+                    .strip_prefix("@slice")
+                    .map(|directive| (directive, true)),
+            };
+            if let Some((directive, slice)) = directive {
+                if in_statement {
+                    continue;
+                }
+                if let Some(mut directive) = parse_param_directive(directive) {
+                    directive.slice = slice;
+                    pending_params.push(directive);
+                }
+                continue;
+            }
+
             let parts: Vec<&str> = comment.splitn(2, ':').collect();
 
             if parts.len() == 2 {
@@ -174,40 +364,22 @@ pub fn extract_query_annotations(sql: &str) -> Vec<Option<QueryAnnotation>> {
 
                 let cardinality = match cardinality_str {
                     "one" => Some(QueryCardinality::One),
+                    "one!" => Some(QueryCardinality::ExactlyOne),
                     "many" => Some(QueryCardinality::Many),
+                    "val" => Some(QueryCardinality::Val),
+                    "column" => Some(QueryCardinality::Column),
+                    "iter" => Some(QueryCardinality::Iter),
                     "exec" => Some(QueryCardinality::Exec),
+                    "execrows" => Some(QueryCardinality::ExecRows),
+                    "execlastid" => Some(QueryCardinality::ExecLastId),
                     _ => None,
                 };
 
                 if let (Some(cardinality), true) = (cardinality, !name.is_empty()) {
-                    let valid_python_ident = name.chars().enumerate().all(|(i, c)| {
-                        if i == 0 {
-                            c.is_alphabetic() || c == '_'
-                        } else {
-                            c.is_alphanumeric() || c == '_'
-                        }
-                    });
-
-                    if !valid_python_ident {
-                        eprintln!(
-                            "Warning: annotation name {:?} is not a valid Python identifier, skipping",
-                            name
-                        );
-                        continue;
-                    }
-
-                    if is_python_keyword(name) {
-                        eprintln!(
-                            "Warning: annotation name {:?} is a Python keyword and cannot be \
-                             used as a function name, skipping",
-                            name
-                        );
-                        continue;
-                    }
-
                     pending_annotation = Some(QueryAnnotation {
                         name: name.to_string(),
                         cardinality,
+                        params: Vec::new(),
                     });
                     in_statement = false;
                 }
@@ -215,6 +387,12 @@ pub fn extract_query_annotations(sql: &str) -> Vec<Option<QueryAnnotation>> {
             continue;
         }
 
+        if !in_statement {
+            if let Some(annotation) = &mut pending_annotation {
+                annotation.params = std::mem::take(&mut pending_params);
+            }
+            pending_params.clear();
+        }
         in_statement = true;
 
         if trimmed.ends_with(';') {
@@ -230,87 +408,104 @@ pub fn extract_query_annotations(sql: &str) -> Vec<Option<QueryAnnotation>> {
     annotations
 }
 
+/// Parse the text after `@param` or `@slice`: `name [as new_name] [type][?]`.
+/// The type runs to the end of the line so multi-word SQL types can be given.
+fn parse_param_directive(text: &str) -> Option<ParamDirective> {
+    let mut tokens = text.split_whitespace().peekable();
+    let name = tokens.next()?.to_string();
+
+    let rename = if tokens.peek() == Some(&"as") {
+        tokens.next();
+        Some(tokens.next()?.to_string())
+    } else {
+        None
+    };
+
+    let rest: Vec<&str> = tokens.collect();
+    let rest = rest.join(" ");
+    let (data_type, nullable) = match rest.strip_suffix('?') {
+        Some(data_type) => (data_type.trim(), true),
+        None => (rest.as_str(), false),
+    };
+
+    Some(ParamDirective {
+        name,
+        rename,
+        data_type: (!data_type.is_empty()).then(|| data_type.to_string()),
+        nullable,
+        slice: false,
+    })
+}
+
 // ---------------------------------------------------------------------------
 // Placeholder extraction
 // ---------------------------------------------------------------------------
 
-/// Recursively walk an expression tree and collect all placeholder strings.
-pub fn collect_placeholders(expr: &Expr, out: &mut Vec<String>) {
-    match expr {
-        Expr::Value(v) => {
-            if let Value::Placeholder(name) = &v.value {
-                out.push(name.clone());
-            }
-        }
-        Expr::BinaryOp { left, right, .. } => {
-            collect_placeholders(left, out);
-            collect_placeholders(right, out);
-        }
-        Expr::UnaryOp { expr, .. } => collect_placeholders(expr, out),
-        Expr::Between {
-            expr, low, high, ..
-        } => {
-            collect_placeholders(expr, out);
-            collect_placeholders(low, out);
-            collect_placeholders(high, out);
-        }
-        Expr::InList { expr, list, .. } => {
-            collect_placeholders(expr, out);
-            for e in list {
-                collect_placeholders(e, out);
-            }
-        }
-        Expr::IsNull(e) | Expr::IsNotNull(e) => collect_placeholders(e, out),
-        Expr::Like { expr, pattern, .. }
-        | Expr::ILike { expr, pattern, .. }
-        | Expr::SimilarTo { expr, pattern, .. } => {
-            collect_placeholders(expr, out);
-            collect_placeholders(pattern, out);
-        }
-        Expr::Case {
-            operand,
-            conditions,
-            else_result,
-            ..
-        } => {
-            if let Some(op) = operand {
-                collect_placeholders(op, out);
-            }
-            for condition in conditions {
-                collect_placeholders(&condition.condition, out);
-                collect_placeholders(&condition.result, out);
-            }
-            if let Some(e) = else_result {
-                collect_placeholders(e, out);
-            }
-        }
-        Expr::Function(f) => {
-            if let sqlparser::ast::FunctionArguments::List(arg_list) = &f.args {
-                for arg in &arg_list.args {
-                    match arg {
-                        sqlparser::ast::FunctionArg::Named { arg, .. }
-                        | sqlparser::ast::FunctionArg::Unnamed(arg) => {
-                            if let sqlparser::ast::FunctionArgExpr::Expr(e) = arg {
-                                collect_placeholders(e, out);
-                            }
-                        }
-                        sqlparser::ast::FunctionArg::ExprNamed { arg, .. } => {
-                            if let sqlparser::ast::FunctionArgExpr::Expr(e) = arg {
-                                collect_placeholders(e, out);
-                            }
-                        }
-                    }
-                }
+/// Collect every placeholder in `node` along with where it starts in the SQL
+/// source, in the order they appear in the text.
+///
+/// The whole tree is visited, so placeholders inside subqueries, `ORDER BY`,
+/// `LIMIT`, `ON CONFLICT` and the like are all found.
+pub fn collect_placeholder_locations<V: Visit>(node: &V) -> Vec<(Location, String)> {
+    let mut placeholders: Vec<(Location, String)> = Vec::new();
+    let _ = visit_expressions(node, |expr| {
+        if let Expr::Value(v) = expr {
+            if let Value::Placeholder(placeholder) = &v.value {
+                placeholders.push((v.span.start, placeholder.clone()));
             }
         }
-        _ => {}
-    }
+        ControlFlow::<()>::Continue(())
+    });
+    // The visitor walks fields in declaration order, which doesn't always
+    // match the text; `?` markers are bound by textual position.
+    placeholders.sort_by_key(|(location, _)| *location);
+    placeholders
+}
+
+/// Collect every placeholder in `node`, in the order they appear in the text.
+pub fn collect_placeholders<V: Visit>(node: &V) -> Vec<String> {
+    collect_placeholder_locations(node)
+        .into_iter()
+        .map(|(_, placeholder)| placeholder)
+        .collect()
 }
 
 // ---------------------------------------------------------------------------
 // Input field building
 // ---------------------------------------------------------------------------
 
+/// The input field name a placeholder binds to: `:name` → `name`, `$1` → `p1`
+/// and the n-th `?` → `pn`. `anon_counter` counts the `?`s seen so far.
+pub fn placeholder_field_name(placeholder: &str, anon_counter: &mut usize) -> String {
+    if placeholder == "?" {
+        *anon_counter += 1;
+        format!("p{}", anon_counter)
+    } else if let Some(name) = placeholder.strip_prefix(':') {
+        name.to_string()
+    } else if placeholder.starts_with('$') {
+        // Normalize "$1" -> "p1" so the generated identifier is valid.
+        format!("p{}", placeholder.trim_start_matches('$'))
+    } else {
+        placeholder.to_string()
+    }
+}
+
+/// The input field name of every placeholder in `statement`, keyed by where
+/// the placeholder starts. `?` markers are numbered in textual order, not the
+/// order a visitor reaches them.
+pub fn placeholder_names(statement: &Statement) -> HashMap<Location, String> {
+    let mut anon_counter = 0usize;
+    collect_placeholder_locations(statement)
+        .into_iter()
+        .map(|(location, placeholder)| {
+            (
+                location,
+                placeholder_field_name(&placeholder, &mut anon_counter),
+            )
+        })
+        .collect()
+}
+
 pub fn build_input_fields(
     raw: &[String],
     active_tables: &[&str],
@@ -321,80 +516,108 @@ pub fn build_input_fields(
     let mut anon_counter = 0usize;
 
     for placeholder in raw {
+        let name = placeholder_field_name(placeholder, &mut anon_counter);
+        if seen.contains(&name) {
+            continue;
+        }
+        seen.push(name.clone());
+        let placeholder_name = name.clone();
+
         if placeholder == "?" {
-            anon_counter += 1;
-            let name = format!("p{}", anon_counter);
-            if !seen.contains(&name) {
-                seen.push(name.clone());
-                fields.push(QueryInputField {
-                    name,
-                    data_type: "Any".to_string(),
-                    placeholder_kind: PlaceholderKind::QuestionMark,
-                });
-            }
-        } else if let Some(name) = placeholder.strip_prefix(':') {
-            if !seen.contains(&name.to_string()) {
-                seen.push(name.to_string());
-                let data_type = resolve_param_type(name, active_tables, schema);
-                fields.push(QueryInputField {
-                    name: name.to_string(),
-                    data_type,
-                    placeholder_kind: PlaceholderKind::Named,
-                });
-            }
+            fields.push(QueryInputField {
+                name,
+                data_type: "Any".to_string(),
+                sql_type: None,
+                nullable: false,
+                placeholder_kind: PlaceholderKind::QuestionMark,
+                placeholder_name,
+                slice: None,
+            });
+        } else if placeholder.starts_with(':') {
+            let column = resolve_param_column(&name, active_tables, schema);
+            fields.push(QueryInputField {
+                data_type: column
+                    .map(|c| sql_type_to_python(&c.data_type))
+                    .unwrap_or("Any")
+                    .to_string(),
+                sql_type: column.map(|c| c.data_type.clone()),
+                nullable: column.map(|c| c.nullable).unwrap_or(false),
+                name,
+                placeholder_kind: PlaceholderKind::Named,
+                placeholder_name,
+                slice: None,
+            });
         } else if placeholder.starts_with('$') {
-            // Normalize "$1" -> "p1" so the generated Python identifier is valid.
-            let name = format!("p{}", placeholder.trim_start_matches('$'));
-            if !seen.contains(&name) {
-                seen.push(name.clone());
-                fields.push(QueryInputField {
-                    name,
-                    data_type: "Any".to_string(),
-                    placeholder_kind: PlaceholderKind::Dollar,
-                });
-            }
+            fields.push(QueryInputField {
+                name,
+                data_type: "Any".to_string(),
+                sql_type: None,
+                nullable: false,
+                placeholder_kind: PlaceholderKind::Dollar,
+                placeholder_name,
+                slice: None,
+            });
         } else {
-            if !seen.contains(placeholder) {
-                seen.push(placeholder.clone());
-                fields.push(QueryInputField {
-                    name: placeholder.clone(),
-                    data_type: "Any".to_string(),
-                    placeholder_kind: PlaceholderKind::Named,
-                });
-            }
+            fields.push(QueryInputField {
+                name,
+                data_type: "Any".to_string(),
+                sql_type: None,
+                nullable: false,
+                placeholder_kind: PlaceholderKind::Named,
+                placeholder_name,
+                slice: None,
+            });
         }
     }
 
     fields
 }
 
-pub fn resolve_param_type(
+/// Find the column a named parameter binds to: the column of the same name
+/// in the active tables, provided exactly one table has it.
+pub fn resolve_param_column<'a>(
     param_name: &str,
     active_tables: &[&str],
-    schema: &SchemaParseResult,
-) -> String {
-    let mut matches: Vec<&str> = Vec::new();
+    schema: &'a SchemaParseResult,
+) -> Option<&'a Column> {
+    let mut matches: Vec<&Column> = Vec::new();
 
     for &table_name in active_tables {
-        if let Some(columns) = schema.table_fields.get(table_name) {
-            if let Some(sql_type) = columns.get(param_name) {
-                matches.push(sql_type.as_str());
-            }
+        if let Some(column) = schema.column(table_name, param_name) {
+            matches.push(column);
         }
     }
 
     if matches.len() == 1 {
-        sql_type_to_python(matches[0]).to_string()
+        Some(matches[0])
     } else {
-        "Any".to_string()
+        None
     }
 }
 
 // ---------------------------------------------------------------------------
-// SQL → Python type mapping
+// SQL type classification
 // ---------------------------------------------------------------------------
 
-pub fn sql_type_to_python(sql_type: &str) -> &'static str {
+/// A language-neutral category for a declared SQL type. Each codegen backend
+/// maps these to its own native types.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SqlTypeClass {
+    Integer,
+    Text,
+    Float,
+    Boolean,
+    Bytes,
+    Decimal,
+    Date,
+    Time,
+    Timestamp,
+    Uuid,
+    Json,
+    Unknown,
+}
+
+pub fn classify_sql_type(sql_type: &str) -> SqlTypeClass {
     let upper = sql_type.to_uppercase();
     let normalised = if let Some(pos) = upper.find('(') {
         upper[..pos].trim()
@@ -405,24 +628,27 @@ pub fn sql_type_to_python(sql_type: &str) -> &'static str {
     match normalised {
         "INTEGER" | "INT" | "INT2" | "INT4" | "INT8" | "INT16" | "INT32" | "INT64" | "BIGINT"
         | "SMALLINT" | "TINYINT" | "MEDIUMINT" | "BYTEINT" | "HUGEINT" | "UBIGINT"
-        | "USMALLINT" | "UTINYINT" | "UINTEGER" => "int",
+        | "USMALLINT" | "UTINYINT" | "UINTEGER" | "SERIAL" | "BIGSERIAL" | "SMALLSERIAL"
+        | "SERIAL2" | "SERIAL4" | "SERIAL8" => SqlTypeClass::Integer,
 
         "TEXT" | "VARCHAR" | "CHAR" | "CHARACTER VARYING" | "CHARACTER" | "CLOB" | "TINYTEXT"
-        | "MEDIUMTEXT" | "LONGTEXT" | "STRING" | "NCHAR" | "NVARCHAR" | "NCLOB" | "BPCHAR" => "str",
+        | "MEDIUMTEXT" | "LONGTEXT" | "STRING" | "NCHAR" | "NVARCHAR" | "NCLOB" | "BPCHAR" => {
+            SqlTypeClass::Text
+        }
 
         "REAL" | "FLOAT" | "FLOAT4" | "FLOAT8" | "FLOAT16" | "FLOAT32" | "FLOAT64" | "DOUBLE"
-        | "DOUBLE PRECISION" => "float",
+        | "DOUBLE PRECISION" => SqlTypeClass::Float,
 
-        "BOOLEAN" | "BOOL" => "bool",
+        "BOOLEAN" | "BOOL" => SqlTypeClass::Boolean,
 
         "BLOB" | "BYTEA" | "BINARY" | "VARBINARY" | "TINYBLOB" | "MEDIUMBLOB" | "LONGBLOB"
-        | "BIT" | "BIT VARYING" => "bytes",
+        | "BIT" | "BIT VARYING" => SqlTypeClass::Bytes,
 
-        "NUMERIC" | "DECIMAL" | "DEC" | "MONEY" | "SMALLMONEY" => "Decimal",
+        "NUMERIC" | "DECIMAL" | "DEC" | "MONEY" | "SMALLMONEY" => SqlTypeClass::Decimal,
 
-        "DATE" => "datetime.date",
+        "DATE" => SqlTypeClass::Date,
 
-        "TIME" | "TIMETZ" | "TIME WITH TIME ZONE" | "TIME WITHOUT TIME ZONE" => "datetime.time",
+        "TIME" | "TIMETZ" | "TIME WITH TIME ZONE" | "TIME WITHOUT TIME ZONE" => SqlTypeClass::Time,
 
         "TIMESTAMP"
         | "TIMESTAMPTZ"
@@ -431,34 +657,54 @@ pub fn sql_type_to_python(sql_type: &str) -> &'static str {
         | "DATETIME"
         | "DATETIME2"
         | "SMALLDATETIME"
-        | "DATETIMEOFFSET" => "datetime.datetime",
+        | "DATETIMEOFFSET" => SqlTypeClass::Timestamp,
 
-        "UUID" => "str",
+        "UUID" => SqlTypeClass::Uuid,
 
-        "JSON" | "JSONB" => "Any",
+        "JSON" | "JSONB" => SqlTypeClass::Json,
 
         _ => {
+            // SQLite-style type affinity rules for everything else
             if normalised.contains("INT") {
-                "int"
+                SqlTypeClass::Integer
             } else if normalised.contains("CHAR")
                 || normalised.contains("CLOB")
                 || normalised.contains("TEXT")
             {
-                "str"
+                SqlTypeClass::Text
             } else if normalised.contains("REAL")
                 || normalised.contains("FLOA")
                 || normalised.contains("DOUB")
             {
-                "float"
+                SqlTypeClass::Float
             } else if normalised.contains("BLOB") || normalised.is_empty() {
-                "bytes"
+                SqlTypeClass::Bytes
             } else {
-                "Any"
+                SqlTypeClass::Unknown
             }
         }
     }
 }
 
+// ---------------------------------------------------------------------------
+// SQL → Python type mapping
+// ---------------------------------------------------------------------------
+
+pub fn sql_type_to_python(sql_type: &str) -> &'static str {
+    match classify_sql_type(sql_type) {
+        SqlTypeClass::Integer => "int",
+        SqlTypeClass::Text | SqlTypeClass::Uuid => "str",
+        SqlTypeClass::Float => "float",
+        SqlTypeClass::Boolean => "bool",
+        SqlTypeClass::Bytes => "bytes",
+        SqlTypeClass::Decimal => "Decimal",
+        SqlTypeClass::Date => "datetime.date",
+        SqlTypeClass::Time => "datetime.time",
+        SqlTypeClass::Timestamp => "datetime.datetime",
+        SqlTypeClass::Json | SqlTypeClass::Unknown => "Any",
+    }
+}
+
 // ---------------------------------------------------------------------------
 // Statement processing
 // ---------------------------------------------------------------------------
@@ -468,168 +714,144 @@ pub fn process_sql_statement(
     annotation: QueryAnnotation,
     schema: &SchemaParseResult,
 ) -> Result<QueryParseResult, QueryError> {
-    let mut raw_placeholders: Vec<String> = Vec::new();
     let mut active_tables: Vec<String> = Vec::new();
     let mut output_fields: Vec<QueryOutputField> = Vec::new();
+    // Relations the statement itself brings into scope for parameter typing,
+    // on top of those of each query it contains.
+    let mut frame = ParamFrame::new(Location::empty());
+    let mut param_types: HashMap<Location, ExprType> = HashMap::new();
+    let mut param_schema: Option<SchemaParseResult> = None;
 
     match statement {
         Statement::Query(query) => {
-            let select = match query.body.as_select() {
-                Some(s) => s,
-                None => {
-                    eprintln!(
-                        "Unsupported query type: only simple SELECT statements are supported \
-                         (UNION, VALUES, etc. are not yet handled)"
-                    );
-                    return Ok(QueryParseResult {
-                        statement: statement.clone(),
-                        annotation,
-                        input_fields: vec![],
-                        output_fields: vec![],
-                    });
-                }
-            };
-            let mut aliases: HashMap<String, String> = HashMap::new();
-
-            for table_with_joins in &select.from {
-                aliases.extend(extract_aliases_using_relation(&table_with_joins.relation));
-                collect_table_name(&table_with_joins.relation, &mut active_tables);
-
-                for join in &table_with_joins.joins {
-                    aliases.extend(extract_aliases_using_relation(&join.relation));
-                    collect_table_name(&join.relation, &mut active_tables);
-                }
-            }
-
-            for entry in select.projection.iter() {
-                match extract_output_fields_from_select_item(entry, &aliases) {
-                    Ok(fields) => output_fields.extend(fields),
-                    Err(e) => return Err(e),
+            let analysis = analyze_query(query, schema)?;
+            output_fields = analysis.output_fields;
+            active_tables = analysis.tables;
+            if !analysis.relations.is_empty() {
+                let mut scope = schema.clone();
+                for relation in analysis.relations {
+                    scope.register(relation, false);
                 }
-                // Also collect placeholders from projection expressions
-                match entry {
-                    SelectItem::UnnamedExpr(expr) | SelectItem::ExprWithAlias { expr, .. } => {
-                        collect_placeholders(expr, &mut raw_placeholders);
-                    }
-                    _ => {}
-                }
-            }
-
-            // WHERE clause
-            if let Some(expr) = &select.selection {
-                collect_placeholders(expr, &mut raw_placeholders);
+                param_schema = Some(scope);
             }
+        }
+        Statement::Insert(insert) => {
+            let table_name = match &insert.table {
+                TableObject::TableName(name) => object_name_key(name),
+                table => table.to_string(),
+            };
+            active_tables.push(table_name.clone());
+            frame.tables.push(table_name.clone());
 
-            // JOIN ON clauses
-            for table_with_joins in &select.from {
-                for join in &table_with_joins.joins {
-                    if let JoinConstraint::On(expr) = match &join.join_operator {
-                        sqlparser::ast::JoinOperator::Join(c)
-                        | sqlparser::ast::JoinOperator::Inner(c)
-                        | sqlparser::ast::JoinOperator::Left(c)
-                        | sqlparser::ast::JoinOperator::LeftOuter(c)
-                        | sqlparser::ast::JoinOperator::Right(c)
-                        | sqlparser::ast::JoinOperator::RightOuter(c)
-                        | sqlparser::ast::JoinOperator::FullOuter(c) => c,
-                        _ => continue,
-                    } {
-                        collect_placeholders(expr, &mut raw_placeholders);
-                    }
-                }
+            let mut aliases: HashMap<String, String> = HashMap::new();
+            if let Some(alias) = &insert.table_alias {
+                aliases.insert(alias.value.clone(), table_name.clone());
             }
+            frame.aliases.extend(aliases.clone());
 
-            // HAVING clause
-            if let Some(expr) = &select.having {
-                collect_placeholders(expr, &mut raw_placeholders);
+            if let Some(returning) = &insert.returning {
+                output_fields = analyze_returning(returning, &aliases, &active_tables, schema)?;
             }
-            let active_table_refs_for_resolution: Vec<&str> =
-                active_tables.iter().map(|s| s.as_str()).collect();
 
-            for output_field in output_fields.iter_mut() {
-                if output_field.source.table.is_none() {
-                    let resolved_fields = schema.resolve_fields_in_tables(
-                        &output_field.source.field,
-                        &active_table_refs_for_resolution,
+            if let Some(source) = &insert.source {
+                if let SetExpr::Values(values) = source.body.as_ref() {
+                    bind_insert_value_types(
+                        &table_name,
+                        &insert.columns,
+                        &values.rows,
+                        schema,
+                        &mut param_types,
                     );
-
-                    if resolved_fields.len() == 0 {
-                        return Err(QueryError::InvalidFieldReference {
-                            field_name: output_field.source.field.clone(),
-                        });
-                    } else if resolved_fields.len() > 1 {
-                        return Err(QueryError::AmbiguousFieldReference {
-                            field_name: output_field.source.field.clone(),
-                            candidates: resolved_fields.clone(),
-                        });
-                    } else {
-                        match resolved_fields.first().unwrap() {
-                            FieldSource::TableSource {
-                                database,
-                                schema,
-                                table,
-                                column,
-                                data_type: _,
-                            } => {
-                                output_field.source.database = database.clone();
-                                output_field.source.schema = schema.clone();
-                                output_field.source.table = Some(table.clone());
-                                output_field.source.field = column.clone();
-                            }
-                        }
-                    }
                 }
             }
-        }
-        Statement::Insert(insert) => {
-            active_tables.push(insert.table.to_string());
 
-            if let Some(source) = &insert.source {
-                if let SetExpr::Values(values) = source.body.as_ref() {
-                    for row in &values.rows {
-                        for expr in row {
-                            collect_placeholders(expr, &mut raw_placeholders);
-                        }
-                    }
+            match &insert.on {
+                Some(OnInsert::DuplicateKeyUpdate(assignments)) => {
+                    bind_assignment_types(assignments, &active_tables, schema, &mut param_types);
+                }
+                Some(OnInsert::OnConflict(OnConflict {
+                    action: OnConflictAction::DoUpdate(update),
+                    ..
+                })) => {
+                    bind_assignment_types(
+                        &update.assignments,
+                        &active_tables,
+                        schema,
+                        &mut param_types,
+                    );
                 }
+                _ => {}
             }
         }
         Statement::Update {
             table,
             assignments,
-            selection,
+            from,
+            returning,
             ..
         } => {
-            active_tables.push(table.to_string());
-
-            for assignment in assignments {
-                collect_placeholders(&assignment.value, &mut raw_placeholders);
+            collect_table_name(&table.relation, &mut active_tables);
+            frame.add_relations(table);
+            if let Some(
+                UpdateTableFromKind::BeforeSet(tables) | UpdateTableFromKind::AfterSet(tables),
+            ) = from
+            {
+                for table_with_joins in tables {
+                    frame.add_relations(table_with_joins);
+                }
             }
 
-            if let Some(expr) = selection {
-                collect_placeholders(expr, &mut raw_placeholders);
+            bind_assignment_types(assignments, &active_tables, schema, &mut param_types);
+
+            if let Some(returning) = returning {
+                let aliases = extract_aliases_using_relation(&table.relation);
+                output_fields = analyze_returning(returning, &aliases, &active_tables, schema)?;
             }
         }
         Statement::Delete(delete) => {
             for table_ref in &delete.tables {
-                active_tables.push(table_ref.to_string());
+                active_tables.push(object_name_key(table_ref));
             }
 
             let from_tables = match &delete.from {
                 FromTable::WithFromKeyword(tables) | FromTable::WithoutKeyword(tables) => tables,
             };
+            let mut aliases: HashMap<String, String> = HashMap::new();
             for table_with_joins in from_tables {
+                aliases.extend(extract_aliases_using_relation(&table_with_joins.relation));
                 collect_table_name(&table_with_joins.relation, &mut active_tables);
+                frame.add_relations(table_with_joins);
             }
 
-            if let Some(expr) = &delete.selection {
-                collect_placeholders(expr, &mut raw_placeholders);
+            if let Some(returning) = &delete.returning {
+                output_fields = analyze_returning(returning, &aliases, &active_tables, schema)?;
             }
         }
         _ => {}
     };
 
+    if annotation.cardinality.is_scalar() && output_fields.len() != 1 {
+        return Err(QueryError::ScalarColumnCount {
+            cardinality: annotation.cardinality.to_string(),
+            found: output_fields.len(),
+        });
+    }
+
+    let mut visitor = ParamTypeVisitor {
+        schema: param_schema.as_ref().unwrap_or(schema),
+        frames: vec![vec![frame]],
+        types: param_types,
+    };
+    let _ = statement.visit(&mut visitor);
+
+    let placeholders = collect_placeholder_locations(statement);
+    check_placeholder_kinds(&placeholders)?;
+    let raw_placeholders = collect_placeholders(statement);
     let active_table_refs: Vec<&str> = active_tables.iter().map(|s| s.as_str()).collect();
-    let input_fields = build_input_fields(&raw_placeholders, &active_table_refs, schema);
+    let mut input_fields = build_input_fields(&raw_placeholders, &active_table_refs, schema);
+    apply_param_types(&mut input_fields, &placeholders, &visitor.types);
+    apply_param_directives(&mut input_fields, &annotation.params)?;
+    check_slice_placements(statement, &placeholders, &mut input_fields)?;
 
     Ok(QueryParseResult {
         statement: statement.clone(),
@@ -639,128 +861,1581 @@ pub fn process_sql_statement(
     })
 }
 
-fn collect_table_name(table_factor: &TableFactor, out: &mut Vec<String>) {
-    if let TableFactor::Table { name, .. } = table_factor {
-        let table_name = name.to_string();
-        if !out.contains(&table_name) {
-            out.push(table_name);
-        }
-    }
+/// The output columns of a SELECT along with the tables it reads from and
+/// the CTEs it defines.
+struct SelectAnalysis {
+    output_fields: Vec<QueryOutputField>,
+    tables: Vec<String>,
+    relations: Vec<Table>,
 }
 
-fn extract_aliases_using_relation(table_factor: &TableFactor) -> HashMap<String, String> {
+fn analyze_select(
+    select: &Select,
+    schema: &SchemaParseResult,
+) -> Result<SelectAnalysis, QueryError> {
+    let mut active_tables: Vec<String> = Vec::new();
+    let mut output_fields: Vec<QueryOutputField> = Vec::new();
     let mut aliases: HashMap<String, String> = HashMap::new();
-
-    match table_factor {
-        TableFactor::Table { name, alias, .. } => {
-            let table_name = name.to_string();
-            if let Some(alias) = &alias {
-                aliases.insert(alias.name.to_string(), table_name.clone());
+    let mut nullable_relations: Vec<String> = Vec::new();
+    let mut relations: Vec<(String, String)> = Vec::new();
+
+    for table_with_joins in &select.from {
+        aliases.extend(extract_aliases_using_relation(&table_with_joins.relation));
+        let first = relations.len();
+        collect_relation(&table_with_joins.relation, &mut relations);
+        let mut joined_tables: Vec<String> = Vec::new();
+        collect_table_name(&table_with_joins.relation, &mut joined_tables);
+
+        for join in &table_with_joins.joins {
+            aliases.extend(extract_aliases_using_relation(&join.relation));
+            let joined = relations.len();
+            collect_relation(&join.relation, &mut relations);
+            let mut join_tables: Vec<String> = Vec::new();
+            collect_table_name(&join.relation, &mut join_tables);
+
+            // The optional side of an outer join produces NULLs when
+            // there is no matching row. Relations are tracked by qualifier,
+            // as the same table may also be joined on a preserved side.
+            let qualifiers = |range: std::ops::Range<usize>| {
+                relations[range]
+                    .iter()
+                    .map(|(qualifier, _)| qualifier.clone())
+                    .collect::<Vec<_>>()
             };
+            match &join.join_operator {
+                JoinOperator::Left(_) | JoinOperator::LeftOuter(_) => {
+                    nullable_relations.extend(qualifiers(joined..relations.len()));
+                }
+                JoinOperator::Right(_) | JoinOperator::RightOuter(_) => {
+                    nullable_relations.extend(qualifiers(first..joined));
+                }
+                JoinOperator::FullOuter(_) => {
+                    nullable_relations.extend(qualifiers(first..relations.len()));
+                }
+                _ => {}
+            }
+
+            joined_tables.extend(join_tables);
         }
-        x => {
-            eprintln!("Unsupported: cannot extract aliases from {:?}", x)
+
+        for table in joined_tables {
+            if !active_tables.contains(&table) {
+                active_tables.push(table);
+            }
         }
     }
 
-    aliases
-}
+    let scope = ProjectionScope {
+        aliases: &aliases,
+        tables: &active_tables,
+        relations: &relations,
+        nullable_relations: &nullable_relations,
+        schema,
+    };
 
-fn extract_output_field_from_expr(
-    expr: &Expr,
-    aliases: &HashMap<String, String>,
-) -> Result<QueryOutputField, QueryError> {
-    match expr {
-        Expr::Identifier(ident) => Ok(QueryOutputField {
-            source: QueryOutputFieldSource {
-                database: None,
-                schema: None,
-                table: None,
-                field: ident.to_string(),
-            },
-            name: ident.to_string(),
-        }),
-        Expr::CompoundIdentifier(idents) => match &idents[..] {
-            [alias_or_table, field] => {
-                let mut table = alias_or_table.to_string();
-                if let Some(aliased_table) = aliases.get(&table) {
-                    table = aliased_table.clone();
-                }
-                Ok(QueryOutputField {
-                    source: QueryOutputFieldSource {
-                        database: None,
-                        schema: None,
-                        table: Some(table.to_string()),
-                        field: field.to_string(),
-                    },
-                    name: field.to_string(),
-                })
-            }
-            [database_or_schema, table, field] => Ok(QueryOutputField {
-                source: QueryOutputFieldSource {
-                    database: None,
-                    schema: Some(database_or_schema.to_string()),
-                    table: Some(table.to_string()),
-                    field: field.to_string(),
-                },
-                name: field.to_string(),
-            }),
-            [database, schema, table, field] => Ok(QueryOutputField {
-                source: QueryOutputFieldSource {
-                    database: Some(database.to_string()),
-                    schema: Some(schema.to_string()),
-                    table: Some(table.to_string()),
-                    field: field.to_string(),
-                },
-                name: field.to_string(),
-            }),
-            _ => Err(QueryError::UnsupportedExpression {
-                description: format!(
-                    "unsupported compound identifier with {} parts",
-                    idents.len()
-                ),
-            }),
-        },
-        x => Err(QueryError::UnsupportedExpression {
-            description: format!("computed expression in SELECT not supported: {}", x),
-        }),
+    for entry in select.projection.iter() {
+        match extract_output_fields_from_select_item(entry, &scope, &output_fields) {
+            Ok(fields) => output_fields.extend(fields),
+            Err(e) => return Err(e),
+        }
     }
+
+    Ok(SelectAnalysis {
+        output_fields,
+        tables: active_tables,
+        relations: Vec::new(),
+    })
 }
 
-fn extract_output_fields_from_select_item(
-    select_item: &SelectItem,
+/// Resolve the `RETURNING` list of an INSERT, UPDATE or DELETE against the
+/// tables the statement writes to.
+fn analyze_returning(
+    returning: &[SelectItem],
     aliases: &HashMap<String, String>,
+    tables: &[String],
+    schema: &SchemaParseResult,
 ) -> Result<Vec<QueryOutputField>, QueryError> {
     let mut output_fields: Vec<QueryOutputField> = Vec::new();
+    let relations: Vec<(String, String)> = tables.iter().map(|t| (t.clone(), t.clone())).collect();
+    let scope = ProjectionScope {
+        aliases,
+        tables,
+        relations: &relations,
+        nullable_relations: &[],
+        schema,
+    };
 
-    match select_item {
-        SelectItem::UnnamedExpr(expr) => {
-            output_fields.push(extract_output_field_from_expr(expr, aliases)?);
-        }
-        SelectItem::ExprWithAlias { expr, alias } => {
-            let mut output_field = extract_output_field_from_expr(expr, aliases)?;
-            output_field.name = alias.to_string();
-            output_fields.push(output_field);
-        }
-        SelectItem::QualifiedWildcard(..) | SelectItem::Wildcard(..) => {
-            return Err(QueryError::UnsupportedExpression {
-                description: "wildcard SELECT (*) is not supported; list columns explicitly"
-                    .to_string(),
-            });
-        }
+    for entry in returning {
+        let fields = extract_output_fields_from_select_item(entry, &scope, &output_fields)?;
+        output_fields.extend(fields);
     }
 
     Ok(output_fields)
 }
 
-// ---------------------------------------------------------------------------
-// Tests
-// ---------------------------------------------------------------------------
+/// Resolve the output columns of a query, as used for `CREATE VIEW ... AS`.
+pub fn analyze_query_output(
+    query: &Query,
+    schema: &SchemaParseResult,
+) -> Result<Vec<QueryOutputField>, QueryError> {
+    Ok(analyze_query(query, schema)?.output_fields)
+}
 
-#[cfg(test)]
-mod tests {
+/// Analyse a query along with its `WITH` clause. Each CTE is analysed in turn
+/// and registered as a temporary relation that later CTEs and the query body
+/// can select from.
+fn analyze_query(query: &Query, schema: &SchemaParseResult) -> Result<SelectAnalysis, QueryError> {
+    let with = match &query.with {
+        Some(with) => with,
+        None => return analyze_set_expr(&query.body, schema),
+    };
+
+    let mut scope = schema.clone();
+    let mut tables: Vec<String> = Vec::new();
+    let mut relations: Vec<Table> = Vec::new();
+
+    for cte in &with.cte_tables {
+        let (relation, analysis) = analyze_cte(cte, with.recursive, &scope)?;
+        merge_tables(&mut tables, analysis.tables);
+        relations.extend(analysis.relations);
+        relations.push(relation.clone());
+        scope.register(relation, false);
+    }
+
+    let mut analysis = analyze_set_expr(&query.body, &scope)?;
+    merge_tables(&mut tables, analysis.tables);
+    relations.append(&mut analysis.relations);
+    analysis.tables = tables;
+    analysis.relations = relations;
+    Ok(analysis)
+}
+
+fn analyze_set_expr(
+    body: &SetExpr,
+    schema: &SchemaParseResult,
+) -> Result<SelectAnalysis, QueryError> {
+    match body {
+        SetExpr::Select(select) => analyze_select(select, schema),
+        SetExpr::Query(query) => analyze_query(query, schema),
+        SetExpr::SetOperation {
+            op, left, right, ..
+        } => {
+            let mut analysis = analyze_set_expr(left, schema)?;
+            let other = analyze_set_expr(right, schema)?;
+            combine_set_operation_fields(op, &mut analysis.output_fields, &other.output_fields)?;
+            merge_tables(&mut analysis.tables, other.tables);
+            analysis.relations.extend(other.relations);
+            Ok(analysis)
+        }
+        _ => Err(QueryError::UnsupportedExpression {
+            description: "only SELECT statements and set operations over them are supported \
+                          (VALUES, TABLE, etc. are not yet handled)"
+                .to_string(),
+        }),
+    }
+}
+
+/// Check that the branches of a `UNION`, `INTERSECT` or `EXCEPT` line up.
+/// Output names and types come from the first branch; a column is nullable
+/// when it is nullable in any branch.
+fn combine_set_operation_fields(
+    op: &SetOperator,
+    fields: &mut [QueryOutputField],
+    other: &[QueryOutputField],
+) -> Result<(), QueryError> {
+    if fields.len() != other.len() {
+        return Err(QueryError::IncompatibleSetOperation {
+            operator: op.to_string(),
+            reason: format!(
+                "the first branch has {} columns but another has {}",
+                fields.len(),
+                other.len()
+            ),
+        });
+    }
+
+    for (field, other_field) in fields.iter_mut().zip(other) {
+        if let (Some(sql_type), Some(other_type)) = (&field.sql_type, &other_field.sql_type) {
+            if !are_set_operation_types_compatible(sql_type, other_type) {
+                return Err(QueryError::IncompatibleSetOperation {
+                    operator: op.to_string(),
+                    reason: format!(
+                        "column \"{}\" is {} in the first branch but {} in another",
+                        field.name, sql_type, other_type
+                    ),
+                });
+            }
+        }
+        field.nullable = field.nullable || other_field.nullable;
+    }
+
+    Ok(())
+}
+
+/// Types are compatible when they classify the same, when either is unknown,
+/// or when both are numeric.
+fn are_set_operation_types_compatible(left: &str, right: &str) -> bool {
+    let is_numeric = |class: SqlTypeClass| {
+        matches!(
+            class,
+            SqlTypeClass::Integer | SqlTypeClass::Float | SqlTypeClass::Decimal
+        )
+    };
+    let (left, right) = (classify_sql_type(left), classify_sql_type(right));
+    left == right
+        || left == SqlTypeClass::Unknown
+        || right == SqlTypeClass::Unknown
+        || (is_numeric(left) && is_numeric(right))
+}
+
+/// Analyse one CTE and build the temporary relation it defines.
+///
+/// A recursive CTE reads from itself, so its columns are taken from the
+/// anchor (the first branch of the `UNION`) before the recursive branch is
+/// analysed against them.
+fn analyze_cte(
+    cte: &Cte,
+    recursive: bool,
+    schema: &SchemaParseResult,
+) -> Result<(Table, SelectAnalysis), QueryError> {
+    let name = cte.alias.name.value.clone();
+    let column_names: Vec<String> = cte
+        .alias
+        .columns
+        .iter()
+        .map(|column| column.name.value.clone())
+        .collect();
+
+    if let (true, None, SetExpr::SetOperation { left, right, .. }) =
+        (recursive, &cte.query.with, cte.query.body.as_ref())
+    {
+        let mut analysis = analyze_set_expr(left, schema)?;
+        let relation = derived_relation(&name, &column_names, &analysis.output_fields)?;
+
+        let mut recursive_scope = schema.clone();
+        recursive_scope.register(relation.clone(), false);
+        let step = analyze_set_expr(right, &recursive_scope)?;
+        if step.output_fields.len() != relation.columns.len() {
+            return Err(QueryError::ColumnCountMismatch {
+                relation: name,
+                expected: relation.columns.len(),
+                found: step.output_fields.len(),
+            });
+        }
+
+        merge_tables(&mut analysis.tables, step.tables);
+        analysis.relations.extend(step.relations);
+        analysis.tables.retain(|table| table != &name);
+        return Ok((relation, analysis));
+    }
+
+    let analysis = analyze_query(&cte.query, schema)?;
+    let relation = derived_relation(&name, &column_names, &analysis.output_fields)?;
+    Ok((relation, analysis))
+}
+
+/// A relation whose columns are the output of a query. `column_names`, when
+/// given, rename the columns by position.
+fn derived_relation(
+    name: &str,
+    column_names: &[String],
+    output_fields: &[QueryOutputField],
+) -> Result<Table, QueryError> {
+    if !column_names.is_empty() && column_names.len() != output_fields.len() {
+        return Err(QueryError::ColumnCountMismatch {
+            relation: name.to_string(),
+            expected: column_names.len(),
+            found: output_fields.len(),
+        });
+    }
+
+    let columns = output_fields
+        .iter()
+        .enumerate()
+        .map(|(index, field)| Column {
+            name: column_names
+                .get(index)
+                .cloned()
+                .unwrap_or_else(|| field.name.clone()),
+            data_type: field.sql_type.clone().unwrap_or_else(unknown_column_type),
+            nullable: field.nullable,
+        })
+        .collect();
+
+    Ok(Table {
+        name: name.to_string(),
+        kind: TableKind::Cte,
+        schema: None,
+        columns,
+        primary_key: Vec::new(),
+        constraints: Vec::new(),
+    })
+}
+
+/// Append the tables in `other` that aren't already in `tables`.
+fn merge_tables(tables: &mut Vec<String>, other: Vec<String>) {
+    for table in other {
+        if !tables.contains(&table) {
+            tables.push(table);
+        }
+    }
+}
+
+/// Resolve an output field against the schema, filling in its source table
+/// and column along with the SQL and Python types.
+///
+/// Unqualified fields are searched for in every active table; qualified fields
+/// are looked up in the table they name.
+fn resolve_output_field(
+    output_field: &mut QueryOutputField,
+    active_tables: &[&str],
+    schema: &SchemaParseResult,
+) -> Result<(), QueryError> {
+    let resolved_fields = match &output_field.source.table {
+        Some(table) => schema.resolve_fields_in_tables(&output_field.source.field, &[table]),
+        None => schema.resolve_fields_in_tables(&output_field.source.field, active_tables),
+    };
+
+    if resolved_fields.is_empty() {
+        return Err(QueryError::InvalidFieldReference {
+            field_name: output_field.source.field.clone(),
+        });
+    } else if resolved_fields.len() > 1 {
+        return Err(QueryError::AmbiguousFieldReference {
+            field_name: output_field.source.field.clone(),
+            candidates: resolved_fields.clone(),
+        });
+    }
+
+    match resolved_fields.first().unwrap() {
+        FieldSource::TableSource {
+            database,
+            schema,
+            table,
+            column,
+            data_type,
+            nullable,
+        } => {
+            if output_field.source.database.is_none() {
+                output_field.source.database = database.clone();
+            }
+            if output_field.source.schema.is_none() {
+                output_field.source.schema = schema.clone();
+            }
+            output_field.source.table = Some(table.clone());
+            output_field.source.field = column.clone();
+            output_field.sql_type = Some(data_type.clone());
+            output_field.python_type = sql_type_to_python(data_type).to_string();
+            output_field.nullable = *nullable;
+        }
+    }
+
+    Ok(())
+}
+
+/// Append a table relation as its qualifier and table name.
+fn collect_relation(table_factor: &TableFactor, out: &mut Vec<(String, String)>) {
+    if let TableFactor::Table { name, alias, .. } = table_factor {
+        let table_name = object_name_key(name);
+        let qualifier = match alias {
+            Some(alias) => alias.name.value.clone(),
+            None => table_name.clone(),
+        };
+        out.push((qualifier, table_name));
+    }
+}
+
+fn collect_table_name(table_factor: &TableFactor, out: &mut Vec<String>) {
+    if let TableFactor::Table { name, .. } = table_factor {
+        let table_name = object_name_key(name);
+        if !out.contains(&table_name) {
+            out.push(table_name);
+        }
+    }
+}
+
+fn extract_aliases_using_relation(table_factor: &TableFactor) -> HashMap<String, String> {
+    let mut aliases: HashMap<String, String> = HashMap::new();
+
+    match table_factor {
+        TableFactor::Table { name, alias, .. } => {
+            let table_name = object_name_key(name);
+            if let Some(alias) = &alias {
+                aliases.insert(alias.name.value.clone(), table_name.clone());
+            };
+        }
+        x => {
+            eprintln!("Unsupported: cannot extract aliases from {:?}", x)
+        }
+    }
+
+    aliases
+}
+
+fn extract_output_field_from_expr(
+    expr: &Expr,
+    aliases: &HashMap<String, String>,
+) -> Result<QueryOutputField, QueryError> {
+    match expr {
+        Expr::Identifier(ident) => Ok(QueryOutputField::unresolved(
+            QueryOutputFieldSource {
+                database: None,
+                schema: None,
+                table: None,
+                field: ident.to_string(),
+            },
+            ident.to_string(),
+        )),
+        Expr::CompoundIdentifier(idents) => match &idents[..] {
+            [alias_or_table, field] => {
+                let mut table = alias_or_table.value.clone();
+                if let Some(aliased_table) = aliases.get(&table) {
+                    table = aliased_table.clone();
+                }
+                Ok(QueryOutputField::unresolved(
+                    QueryOutputFieldSource {
+                        database: None,
+                        schema: None,
+                        table: Some(table.to_string()),
+                        field: field.to_string(),
+                    },
+                    field.to_string(),
+                ))
+            }
+            [database_or_schema, table, field] => Ok(QueryOutputField::unresolved(
+                QueryOutputFieldSource {
+                    database: None,
+                    schema: Some(database_or_schema.value.clone()),
+                    table: Some(table.value.clone()),
+                    field: field.to_string(),
+                },
+                field.to_string(),
+            )),
+            [database, schema, table, field] => Ok(QueryOutputField::unresolved(
+                QueryOutputFieldSource {
+                    database: Some(database.value.clone()),
+                    schema: Some(schema.value.clone()),
+                    table: Some(table.value.clone()),
+                    field: field.to_string(),
+                },
+                field.to_string(),
+            )),
+            _ => Err(QueryError::UnsupportedExpression {
+                description: format!(
+                    "unsupported compound identifier with {} parts",
+                    idents.len()
+                ),
+            }),
+        },
+        x => Err(QueryError::UnsupportedExpression {
+            description: format!("expected a column reference, found {}", x),
+        }),
+    }
+}
+
+/// The relations a projection can refer to.
+struct ProjectionScope<'a> {
+    aliases: &'a HashMap<String, String>,
+    tables: &'a [String],
+    /// Each FROM and JOIN relation as its qualifier (the alias, or the table
+    /// name when it has none) and table, in order. A table joined to itself
+    /// appears once per relation.
+    relations: &'a [(String, String)],
+    /// Qualifiers of the relations on the optional side of an outer join.
+    nullable_relations: &'a [String],
+    schema: &'a SchemaParseResult,
+}
+
+impl ProjectionScope<'_> {
+    /// Resolve a column reference against the scope's tables.
+    fn resolve_column(&self, expr: &Expr) -> Result<QueryOutputField, QueryError> {
+        let mut output_field = extract_output_field_from_expr(expr, self.aliases)?;
+        let tables: Vec<&str> = self.tables.iter().map(|s| s.as_str()).collect();
+        resolve_output_field(&mut output_field, &tables, self.schema)?;
+        let is_nullable = |qualifier: &str| self.nullable_relations.iter().any(|q| q == qualifier);
+        let from_nullable_relation = match expr {
+            // `schema.table.column` is qualified as `schema.table`.
+            Expr::CompoundIdentifier(idents) if idents.len() > 1 => {
+                let qualifier: Vec<&str> = idents[..idents.len() - 1]
+                    .iter()
+                    .rev()
+                    .take(2)
+                    .rev()
+                    .map(|ident| ident.value.as_str())
+                    .collect();
+                is_nullable(&qualifier.join("."))
+            }
+            // An unqualified column belongs to whichever relation reads its
+            // table.
+            _ => self.relations.iter().any(|(qualifier, table)| {
+                is_nullable(qualifier)
+                    && self.schema.table(table).is_some_and(|table| {
+                        Some(&table.name) == output_field.source.table.as_ref()
+                            && table.schema == output_field.source.schema
+                    })
+            }),
+        };
+        if from_nullable_relation {
+            output_field.nullable = true;
+        }
+        Ok(output_field)
+    }
+}
+
+/// Resolve one projection item to its output fields. `previous` holds the
+/// fields of the items before it, so derived names can be made unique.
+fn extract_output_fields_from_select_item(
+    select_item: &SelectItem,
+    scope: &ProjectionScope,
+    previous: &[QueryOutputField],
+) -> Result<Vec<QueryOutputField>, QueryError> {
+    let mut output_fields: Vec<QueryOutputField> = Vec::new();
+
+    match select_item {
+        SelectItem::UnnamedExpr(expr @ (Expr::Identifier(_) | Expr::CompoundIdentifier(_))) => {
+            output_fields.push(scope.resolve_column(expr)?);
+        }
+        SelectItem::UnnamedExpr(expr) => {
+            let name = unique_name(derive_expr_name(expr), |name| {
+                previous.iter().any(|field| field.name == name)
+            });
+            output_fields.push(computed_output_field(expr, name, scope)?);
+        }
+        SelectItem::ExprWithAlias {
+            expr: expr @ (Expr::Identifier(_) | Expr::CompoundIdentifier(_)),
+            alias,
+        } => {
+            let mut output_field = scope.resolve_column(expr)?;
+            output_field.name = alias.value.clone();
+            output_fields.push(output_field);
+        }
+        SelectItem::ExprWithAlias { expr, alias } => {
+            output_fields.push(computed_output_field(expr, alias.value.clone(), scope)?);
+        }
+        SelectItem::Wildcard(options) => {
+            check_wildcard_options(options)?;
+            for (qualifier, table) in scope.relations {
+                for field in expand_wildcard(qualifier, table, scope.schema)? {
+                    let mut output_field = scope.resolve_column(&field)?;
+                    // A column the relations share, as in a self-join, would
+                    // otherwise be named twice in the row type.
+                    output_field.name = unique_name(output_field.name, |name| {
+                        previous
+                            .iter()
+                            .chain(&output_fields)
+                            .any(|field| field.name == name)
+                    });
+                    output_fields.push(output_field);
+                }
+            }
+        }
+        SelectItem::QualifiedWildcard(kind, options) => {
+            check_wildcard_options(options)?;
+            let name = match kind {
+                SelectItemQualifiedWildcardKind::ObjectName(name) => object_name_key(name),
+                SelectItemQualifiedWildcardKind::Expr(expr) => {
+                    return Err(QueryError::UnsupportedExpression {
+                        description: format!("wildcard over an expression: {}.*", expr),
+                    });
+                }
+            };
+            let table = scope.aliases.get(&name).cloned().unwrap_or(name.clone());
+            for field in expand_wildcard(&name, &table, scope.schema)? {
+                let mut output_field = scope.resolve_column(&field)?;
+                // `u.*, o.*` names columns the tables share twice, too.
+                output_field.name = unique_name(output_field.name, |name| {
+                    previous
+                        .iter()
+                        .chain(&output_fields)
+                        .any(|field| field.name == name)
+                });
+                output_fields.push(output_field);
+            }
+        }
+    }
+
+    Ok(output_fields)
+}
+
+/// Expand `qualifier.*` into a `qualifier.column` reference per column of
+/// `table`, in declaration order.
+fn expand_wildcard(
+    qualifier: &str,
+    table: &str,
+    schema: &SchemaParseResult,
+) -> Result<Vec<Expr>, QueryError> {
+    let columns = match schema.table_columns(table) {
+        Some(columns) => columns,
+        None => {
+            return Err(QueryError::InvalidFieldReference {
+                field_name: format!("{}.*", qualifier),
+            });
+        }
+    };
+
+    Ok(columns
+        .iter()
+        .map(|column| {
+            Expr::CompoundIdentifier(vec![Ident::new(qualifier), Ident::new(column.name.clone())])
+        })
+        .collect())
+}
+
+/// `EXCLUDE`, `EXCEPT`, `REPLACE`, `RENAME` and `ILIKE` change which columns a
+/// wildcard produces; they aren't modelled, so reject them rather than guess.
+fn check_wildcard_options(options: &WildcardAdditionalOptions) -> Result<(), QueryError> {
+    if options.opt_ilike.is_some()
+        || options.opt_exclude.is_some()
+        || options.opt_except.is_some()
+        || options.opt_replace.is_some()
+        || options.opt_rename.is_some()
+    {
+        return Err(QueryError::UnsupportedExpression {
+            description: "wildcard modifiers (EXCLUDE, EXCEPT, REPLACE, RENAME, ILIKE)".to_string(),
+        });
+    }
+    Ok(())
+}
+
+// ---------------------------------------------------------------------------
+// Expression typing
+// ---------------------------------------------------------------------------
+
+/// The SQL type and nullability of a computed expression. `sql_type` is
+/// `None` when the type can't be inferred.
+struct ExprType {
+    sql_type: Option<String>,
+    nullable: bool,
+}
+
+impl ExprType {
+    fn known(sql_type: &str, nullable: bool) -> ExprType {
+        ExprType {
+            sql_type: Some(sql_type.to_string()),
+            nullable,
+        }
+    }
+
+    fn unknown() -> ExprType {
+        ExprType {
+            sql_type: None,
+            nullable: true,
+        }
+    }
+}
+
+/// Build the output field for a computed projection expression.
+fn computed_output_field(
+    expr: &Expr,
+    name: String,
+    scope: &ProjectionScope,
+) -> Result<QueryOutputField, QueryError> {
+    let expr_type = infer_expr_type(expr, scope)?;
+    Ok(QueryOutputField {
+        source: QueryOutputFieldSource {
+            database: None,
+            schema: None,
+            table: None,
+            field: name.clone(),
+        },
+        name,
+        python_type: expr_type
+            .sql_type
+            .as_deref()
+            .map(sql_type_to_python)
+            .unwrap_or("Any")
+            .to_string(),
+        sql_type: expr_type.sql_type,
+        nullable: expr_type.nullable,
+    })
+}
+
+/// The column name a database gives an unaliased expression: the column for
+/// a (cast) column reference, the function name for a call, and `column`
+/// otherwise.
+fn derive_expr_name(expr: &Expr) -> String {
+    match expr {
+        Expr::Identifier(ident) => ident.value.clone(),
+        Expr::CompoundIdentifier(idents) => idents
+            .last()
+            .map(|ident| ident.value.clone())
+            .unwrap_or_default(),
+        Expr::Nested(inner) | Expr::Cast { expr: inner, .. } => derive_expr_name(inner),
+        Expr::Function(function) => function_name(function).to_lowercase(),
+        Expr::Case { .. } => "case".to_string(),
+        _ => "column".to_string(),
+    }
+}
+
+/// Suffix `name` with `_2`, `_3`, ... until it doesn't collide with an
+/// earlier output field, as told by `is_taken`.
+fn unique_name(name: String, is_taken: impl Fn(&str) -> bool) -> String {
+    if !is_taken(&name) {
+        return name;
+    }
+    (2..)
+        .map(|n| format!("{}_{}", name, n))
+        .find(|candidate| !is_taken(candidate))
+        .unwrap()
+}
+
+fn function_name(function: &sqlparser::ast::Function) -> String {
+    function
+        .name
+        .0
+        .last()
+        .and_then(|part| part.as_ident())
+        .map(|ident| ident.value.to_uppercase())
+        .unwrap_or_default()
+}
+
+/// The expression arguments of a function call; `*` arguments are skipped.
+fn function_args(function: &sqlparser::ast::Function) -> Vec<&Expr> {
+    let mut args: Vec<&Expr> = Vec::new();
+    if let FunctionArguments::List(arg_list) = &function.args {
+        for arg in &arg_list.args {
+            let (FunctionArg::Named { arg, .. }
+            | FunctionArg::ExprNamed { arg, .. }
+            | FunctionArg::Unnamed(arg)) = arg;
+            if let FunctionArgExpr::Expr(expr) = arg {
+                args.push(expr);
+            }
+        }
+    }
+    args
+}
+
+/// Infer the type of an expression from the columns it references.
+/// Expressions that aren't modelled are given an unknown, nullable type
+/// rather than rejected.
+fn infer_expr_type(expr: &Expr, scope: &ProjectionScope) -> Result<ExprType, QueryError> {
+    let expr_type = match expr {
+        Expr::Identifier(_) | Expr::CompoundIdentifier(_) => {
+            let field = scope.resolve_column(expr)?;
+            ExprType {
+                sql_type: field.sql_type,
+                nullable: field.nullable,
+            }
+        }
+        Expr::Nested(inner) => infer_expr_type(inner, scope)?,
+        Expr::Value(value) => match &value.value {
+            Value::Number(number, _) => {
+                if number.contains(['.', 'e', 'E']) {
+                    ExprType::known("REAL", false)
+                } else {
+                    ExprType::known("INTEGER", false)
+                }
+            }
+            Value::SingleQuotedString(_) | Value::DoubleQuotedString(_) => {
+                ExprType::known("TEXT", false)
+            }
+            Value::Boolean(_) => ExprType::known("BOOLEAN", false),
+            Value::Null => ExprType::unknown(),
+            _ => ExprType {
+                sql_type: None,
+                nullable: false,
+            },
+        },
+        Expr::Cast {
+            kind,
+            expr,
+            data_type,
+            ..
+        } => {
+            let inner = infer_expr_type(expr, scope)?;
+            // TRY_CAST and SAFE_CAST yield NULL when the conversion fails.
+            let nullable = inner.nullable || matches!(kind, CastKind::TryCast | CastKind::SafeCast);
+            ExprType::known(&data_type.to_string(), nullable)
+        }
+        Expr::UnaryOp { op, expr } => {
+            let inner = infer_expr_type(expr, scope)?;
+            match op {
+                UnaryOperator::Not => ExprType::known("BOOLEAN", inner.nullable),
+                _ => inner,
+            }
+        }
+        Expr::BinaryOp { left, op, right } => {
+            let left = infer_expr_type(left, scope)?;
+            let right = infer_expr_type(right, scope)?;
+            let nullable = left.nullable || right.nullable;
+            match op {
+                BinaryOperator::Plus
+                | BinaryOperator::Minus
+                | BinaryOperator::Multiply
+                | BinaryOperator::Divide
+                | BinaryOperator::Modulo => ExprType {
+                    sql_type: arithmetic_result_type(
+                        left.sql_type.as_deref(),
+                        right.sql_type.as_deref(),
+                    ),
+                    nullable,
+                },
+                BinaryOperator::StringConcat => ExprType::known("TEXT", nullable),
+                BinaryOperator::Eq
+                | BinaryOperator::NotEq
+                | BinaryOperator::Lt
+                | BinaryOperator::LtEq
+                | BinaryOperator::Gt
+                | BinaryOperator::GtEq
+                | BinaryOperator::And
+                | BinaryOperator::Or => ExprType::known("BOOLEAN", nullable),
+                _ => ExprType {
+                    sql_type: None,
+                    nullable,
+                },
+            }
+        }
+        Expr::IsNull(_)
+        | Expr::IsNotNull(_)
+        | Expr::IsTrue(_)
+        | Expr::IsNotTrue(_)
+        | Expr::IsFalse(_)
+        | Expr::IsNotFalse(_)
+        | Expr::IsDistinctFrom(..)
+        | Expr::IsNotDistinctFrom(..)
+        | Expr::Exists { .. } => ExprType::known("BOOLEAN", false),
+        Expr::InList { expr, .. }
+        | Expr::InSubquery { expr, .. }
+        | Expr::Between { expr, .. }
+        | Expr::Like { expr, .. }
+        | Expr::ILike { expr, .. }
+        | Expr::SimilarTo { expr, .. } => {
+            ExprType::known("BOOLEAN", infer_expr_type(expr, scope)?.nullable)
+        }
+        Expr::Case {
+            conditions,
+            else_result,
+            ..
+        } => {
+            let mut results: Vec<ExprType> = Vec::new();
+            for condition in conditions {
+                results.push(infer_expr_type(&condition.result, scope)?);
+            }
+            match else_result {
+                Some(else_result) => results.push(infer_expr_type(else_result, scope)?),
+                // A CASE without ELSE yields NULL when nothing matches.
+                None => results.push(ExprType::unknown()),
+            }
+            ExprType {
+                sql_type: results.iter().find_map(|r| r.sql_type.clone()),
+                nullable: results.iter().any(|r| r.nullable),
+            }
+        }
+        Expr::Function(function) => infer_function_type(function, scope)?,
+        Expr::Substring { .. } | Expr::Trim { .. } => ExprType::known("TEXT", true),
+        Expr::Ceil { expr, .. } | Expr::Floor { expr, .. } => infer_expr_type(expr, scope)?,
+        Expr::Subquery(query) => {
+            // A scalar subquery yields NULL when it returns no rows.
+            let sql_type = analyze_query(query, scope.schema)
+                .ok()
+                .and_then(|analysis| analysis.output_fields.into_iter().next())
+                .and_then(|field| field.sql_type);
+            ExprType {
+                sql_type,
+                nullable: true,
+            }
+        }
+        _ => ExprType::unknown(),
+    };
+    Ok(expr_type)
+}
+
+fn infer_function_type(
+    function: &sqlparser::ast::Function,
+    scope: &ProjectionScope,
+) -> Result<ExprType, QueryError> {
+    let args = function_args(function);
+    let mut arg_types: Vec<ExprType> = Vec::new();
+    for arg in &args {
+        arg_types.push(infer_expr_type(arg, scope)?);
+    }
+    let first_type = arg_types.first().and_then(|t| t.sql_type.clone());
+    let any_nullable = arg_types.iter().any(|t| t.nullable);
+
+    let expr_type = match function_name(function).as_str() {
+        "COUNT" => ExprType::known("BIGINT", false),
+        // Aggregates other than COUNT are NULL over an empty set.
+        "SUM" | "MIN" | "MAX" | "TOTAL" => ExprType {
+            sql_type: first_type,
+            nullable: true,
+        },
+        "AVG" => match first_type.as_deref().map(classify_sql_type) {
+            Some(SqlTypeClass::Decimal) => ExprType {
+                sql_type: first_type,
+                nullable: true,
+            },
+            _ => ExprType::known("REAL", true),
+        },
+        "COALESCE" | "IFNULL" | "NVL" => ExprType {
+            sql_type: arg_types.iter().find_map(|t| t.sql_type.clone()),
+            nullable: arg_types.iter().all(|t| t.nullable),
+        },
+        "NULLIF" => ExprType {
+            sql_type: first_type,
+            nullable: true,
+        },
+        "LOWER" | "UPPER" | "TRIM" | "LTRIM" | "RTRIM" | "SUBSTR" | "SUBSTRING" | "REPLACE"
+        | "CONCAT" | "CONCAT_WS" | "GROUP_CONCAT" | "STRING_AGG" => {
+            ExprType::known("TEXT", any_nullable)
+        }
+        "LENGTH" | "CHAR_LENGTH" | "CHARACTER_LENGTH" | "OCTET_LENGTH" | "ROW_NUMBER" | "RANK"
+        | "DENSE_RANK" => ExprType::known("BIGINT", any_nullable),
+        "ABS" | "ROUND" | "CEIL" | "CEILING" | "FLOOR" => ExprType {
+            sql_type: first_type,
+            nullable: any_nullable,
+        },
+        _ => ExprType::unknown(),
+    };
+    Ok(expr_type)
+}
+
+/// The result type of an arithmetic operator: the wider of the two numeric
+/// operand types, or `None` when either operand isn't numeric.
+fn arithmetic_result_type(left: Option<&str>, right: Option<&str>) -> Option<String> {
+    let (left, right) = (left?, right?);
+    let rank = |sql_type: &str| match classify_sql_type(sql_type) {
+        SqlTypeClass::Integer => Some(0),
+        SqlTypeClass::Decimal => Some(1),
+        SqlTypeClass::Float => Some(2),
+        _ => None,
+    };
+    let (left_rank, right_rank) = (rank(left)?, rank(right)?);
+    if right_rank > left_rank {
+        Some(right.to_string())
+    } else {
+        Some(left.to_string())
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Parameter typing
+// ---------------------------------------------------------------------------
+
+/// The relations one SELECT, or the statement itself, brings into scope.
+struct ParamFrame {
+    /// Where the SELECT starts; the branches of a set operation are told
+    /// apart by position.
+    start: Location,
+    aliases: HashMap<String, String>,
+    tables: Vec<String>,
+}
+
+impl ParamFrame {
+    fn new(start: Location) -> ParamFrame {
+        ParamFrame {
+            start,
+            aliases: HashMap::new(),
+            tables: Vec::new(),
+        }
+    }
+
+    /// Add a FROM item and the tables joined to it.
+    fn add_relations(&mut self, table_with_joins: &TableWithJoins) {
+        let relations = std::iter::once(&table_with_joins.relation)
+            .chain(table_with_joins.joins.iter().map(|join| &join.relation));
+        for relation in relations {
+            if let TableFactor::Table { .. } = relation {
+                self.aliases
+                    .extend(extract_aliases_using_relation(relation));
+                collect_table_name(relation, &mut self.tables);
+            }
+        }
+    }
+}
+
+/// Add a frame for every SELECT in a query body, in textual order. Nested
+/// queries get frames of their own when they are visited.
+fn collect_select_frames(body: &SetExpr, frames: &mut Vec<ParamFrame>) {
+    match body {
+        SetExpr::Select(select) => {
+            let mut frame = ParamFrame::new(select.select_token.0.span.start);
+            for table_with_joins in &select.from {
+                frame.add_relations(table_with_joins);
+            }
+            frames.push(frame);
+        }
+        SetExpr::SetOperation { left, right, .. } => {
+            collect_select_frames(left, frames);
+            collect_select_frames(right, frames);
+        }
+        _ => {}
+    }
+}
+
+/// Types placeholders from the expressions they are compared with, cast to
+/// or used as a `LIMIT`/`OFFSET`. Column references are resolved against the
+/// innermost query that has them, so correlated subqueries see the columns
+/// of the queries around them.
+struct ParamTypeVisitor<'a> {
+    schema: &'a SchemaParseResult,
+    /// The frames of each enclosing query, innermost last.
+    frames: Vec<Vec<ParamFrame>>,
+    /// Types found so far, keyed by the placeholder's position. The first
+    /// type found for a position wins.
+    types: HashMap<Location, ExprType>,
+}
+
+impl Visitor for ParamTypeVisitor<'_> {
+    type Break = ();
+
+    fn pre_visit_query(&mut self, query: &Query) -> ControlFlow<()> {
+        let mut frames: Vec<ParamFrame> = Vec::new();
+        collect_select_frames(&query.body, &mut frames);
+        self.frames.push(frames);
+
+        let (limit, offset) = match &query.limit_clause {
+            Some(LimitClause::LimitOffset { limit, offset, .. }) => {
+                (limit.as_ref(), offset.as_ref().map(|offset| &offset.value))
+            }
+            Some(LimitClause::OffsetCommaLimit { offset, limit }) => (Some(limit), Some(offset)),
+            None => (None, None),
+        };
+        for expr in limit.into_iter().chain(offset) {
+            if let Some(location) = placeholder_location(expr) {
+                self.types
+                    .entry(location)
+                    .or_insert_with(|| ExprType::known("INTEGER", false));
+            }
+        }
+        ControlFlow::Continue(())
+    }
+
+    fn post_visit_query(&mut self, _query: &Query) -> ControlFlow<()> {
+        self.frames.pop();
+        ControlFlow::Continue(())
+    }
+
+    fn pre_visit_expr(&mut self, expr: &Expr) -> ControlFlow<()> {
+        match expr {
+            Expr::BinaryOp { left, op, right } if is_comparison_operator(op) => {
+                self.bind(left, right);
+                self.bind(right, left);
+            }
+            Expr::IsDistinctFrom(left, right) | Expr::IsNotDistinctFrom(left, right) => {
+                self.bind(left, right);
+                self.bind(right, left);
+            }
+            Expr::Between {
+                expr, low, high, ..
+            } => {
+                self.bind(low, expr);
+                self.bind(high, expr);
+            }
+            Expr::InList { expr, list, .. } => {
+                for item in list {
+                    self.bind(item, expr);
+                }
+            }
+            Expr::Like { expr, pattern, .. } | Expr::ILike { expr, pattern, .. } => {
+                self.bind(pattern, expr);
+            }
+            Expr::Cast {
+                expr, data_type, ..
+            } => {
+                if let Some(location) = placeholder_location(expr) {
+                    self.types
+                        .entry(location)
+                        .or_insert_with(|| ExprType::known(&data_type.to_string(), false));
+                }
+            }
+            _ => {}
+        }
+        ControlFlow::Continue(())
+    }
+}
+
+impl ParamTypeVisitor<'_> {
+    /// Type `target` like `other` when `target` is a placeholder.
+    fn bind(&mut self, target: &Expr, other: &Expr) {
+        let location = match placeholder_location(target) {
+            Some(location) => location,
+            None => return,
+        };
+        if self.types.contains_key(&location) {
+            return;
+        }
+        if let Some(expr_type) = self.resolve_type(other, location) {
+            self.types.insert(location, expr_type);
+        }
+    }
+
+    /// The type of `expr`, which appears at `location`, resolving its columns
+    /// against the innermost frame that has them.
+    fn resolve_type(&self, expr: &Expr, location: Location) -> Option<ExprType> {
+        for frames in self.frames.iter().rev() {
+            let frame = match frames.iter().rev().find(|frame| frame.start <= location) {
+                Some(frame) => frame,
+                None => continue,
+            };
+            let scope = ProjectionScope {
+                aliases: &frame.aliases,
+                tables: &frame.tables,
+                relations: &[],
+                nullable_relations: &[],
+                schema: self.schema,
+            };
+            match infer_expr_type(expr, &scope) {
+                Ok(expr_type) => return Some(expr_type),
+                Err(QueryError::InvalidFieldReference { .. }) => continue,
+                Err(_) => return None,
+            }
+        }
+        None
+    }
+}
+
+fn is_comparison_operator(op: &BinaryOperator) -> bool {
+    matches!(
+        op,
+        BinaryOperator::Eq
+            | BinaryOperator::NotEq
+            | BinaryOperator::Lt
+            | BinaryOperator::LtEq
+            | BinaryOperator::Gt
+            | BinaryOperator::GtEq
+            | BinaryOperator::Spaceship
+    )
+}
+
+/// Where `expr` starts when it is a (possibly parenthesised) placeholder.
+fn placeholder_location(expr: &Expr) -> Option<Location> {
+    match expr {
+        Expr::Nested(inner) => placeholder_location(inner),
+        Expr::Value(v) if matches!(v.value, Value::Placeholder(_)) => Some(v.span.start),
+        _ => None,
+    }
+}
+
+/// Type the placeholders in `INSERT ... VALUES` rows from the columns they
+/// are inserted into. Without a column list, values line up with the
+/// table's columns in declaration order.
+fn bind_insert_value_types(
+    table: &str,
+    columns: &[Ident],
+    rows: &[Vec<Expr>],
+    schema: &SchemaParseResult,
+    types: &mut HashMap<Location, ExprType>,
+) {
+    let targets: Vec<Option<&Column>> = if columns.is_empty() {
+        schema
+            .table_columns(table)
+            .unwrap_or_default()
+            .iter()
+            .map(Some)
+            .collect()
+    } else {
+        columns
+            .iter()
+            .map(|column| schema.column(table, &column.value))
+            .collect()
+    };
+
+    for row in rows {
+        for (expr, target) in row.iter().zip(&targets) {
+            if let (Some(location), Some(column)) = (placeholder_location(expr), target) {
+                types
+                    .entry(location)
+                    .or_insert_with(|| ExprType::known(&column.data_type, column.nullable));
+            }
+        }
+    }
+}
+
+/// Type the placeholders assigned in `SET column = :value` from the column
+/// they are assigned to.
+fn bind_assignment_types(
+    assignments: &[Assignment],
+    tables: &[String],
+    schema: &SchemaParseResult,
+    types: &mut HashMap<Location, ExprType>,
+) {
+    for assignment in assignments {
+        let (location, target) = match (placeholder_location(&assignment.value), &assignment.target)
+        {
+            (Some(location), AssignmentTarget::ColumnName(target)) => (location, target),
+            _ => continue,
+        };
+        let column_name = match target.0.last().and_then(|part| part.as_ident()) {
+            Some(ident) => &ident.value,
+            None => continue,
+        };
+        if let Some(column) = tables
+            .iter()
+            .find_map(|table| schema.column(table, column_name))
+        {
+            types
+                .entry(location)
+                .or_insert_with(|| ExprType::known(&column.data_type, column.nullable));
+        }
+    }
+}
+
+/// Replace the name-based types of `fields` with the types inferred from
+/// where their placeholders are used. A parameter that appears more than
+/// once takes the first type found.
+fn apply_param_types(
+    fields: &mut [QueryInputField],
+    placeholders: &[(Location, String)],
+    types: &HashMap<Location, ExprType>,
+) {
+    let mut typed: Vec<String> = Vec::new();
+    let mut anon_counter = 0usize;
+
+    for (location, placeholder) in placeholders {
+        let name = placeholder_field_name(placeholder, &mut anon_counter);
+        let (sql_type, nullable) = match types.get(location) {
+            Some(ExprType {
+                sql_type: Some(sql_type),
+                nullable,
+            }) if classify_sql_type(sql_type) != SqlTypeClass::Unknown => (sql_type, *nullable),
+            _ => continue,
+        };
+        if typed.contains(&name) {
+            continue;
+        }
+        if let Some(field) = fields.iter_mut().find(|f| f.placeholder_name == name) {
+            field.data_type = sql_type_to_python(sql_type).to_string();
+            field.sql_type = Some(sql_type.clone());
+            field.nullable = nullable;
+            typed.push(name);
+        }
+    }
+}
+
+/// Apply the `@param` directives of a query to its input fields. A
+/// directive that gives a type also sets nullability; `?` on its own only
+/// marks the parameter nullable.
+fn apply_param_directives(
+    fields: &mut [QueryInputField],
+    directives: &[ParamDirective],
+) -> Result<(), QueryError> {
+    for directive in directives {
+        let invalid = |reason: String| QueryError::InvalidParamDirective {
+            name: directive.name.clone(),
+            reason,
+        };
+
+        let mut anon_counter = 0usize;
+        let placeholder_name = placeholder_field_name(&directive.name, &mut anon_counter);
+        let field = fields
+            .iter_mut()
+            .find(|f| f.placeholder_name == placeholder_name)
+            .ok_or_else(|| invalid("the query has no such parameter".to_string()))?;
+
+        if let Some(data_type) = &directive.data_type {
+            match directive_sql_type(data_type) {
+                Some(sql_type) => {
+                    field.data_type = sql_type_to_python(&sql_type).to_string();
+                    field.sql_type = Some(sql_type);
+                }
+                None if data_type.eq_ignore_ascii_case("any") => {
+                    field.data_type = "Any".to_string();
+                    field.sql_type = None;
+                }
+                None => return Err(invalid(format!("unrecognised type \"{}\"", data_type))),
+            }
+            field.nullable = directive.nullable;
+        } else if directive.nullable {
+            field.nullable = true;
+        }
+
+        if let Some(rename) = &directive.rename {
+            let is_identifier = rename
+                .chars()
+                .next()
+                .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+                && rename
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '_');
+            if !is_identifier {
+                return Err(invalid(format!(
+                    "\"{}\" is not a valid parameter name",
+                    rename
+                )));
+            }
+            field.name = rename.clone();
+        }
+
+        if directive.slice {
+            field.slice = Some(SliceBinding::Expanded { negated: false });
+        }
+    }
+
+    for (index, field) in fields.iter().enumerate() {
+        if fields[..index].iter().any(|other| other.name == field.name) {
+            return Err(QueryError::InvalidParamDirective {
+                name: field.placeholder_name.clone(),
+                reason: format!("another parameter is already named \"{}\"", field.name),
+            });
+        }
+    }
+
+    Ok(())
+}
+
+/// The [`PlaceholderKind`] of a placeholder as written.
+fn placeholder_kind(placeholder: &str) -> PlaceholderKind {
+    if placeholder == "?" {
+        PlaceholderKind::QuestionMark
+    } else if placeholder.starts_with('$') {
+        PlaceholderKind::Dollar
+    } else {
+        PlaceholderKind::Named
+    }
+}
+
+/// Reject statements that mix placeholder styles. A driver binds either by
+/// name or by position, so some of the parameters would never be bound.
+fn check_placeholder_kinds(placeholders: &[(Location, String)]) -> Result<(), QueryError> {
+    let Some((_, first)) = placeholders.first() else {
+        return Ok(());
+    };
+    let kind = placeholder_kind(first);
+    if placeholders
+        .iter()
+        .all(|(_, placeholder)| placeholder_kind(placeholder) == kind)
+    {
+        return Ok(());
+    }
+    Err(QueryError::MixedPlaceholders {
+        placeholders: placeholders.to_vec(),
+    })
+}
+
+/// Reject placeholders `dialect` doesn't understand, such as `$1` in MySQL.
+pub fn check_dialect_placeholders(
+    statement: &Statement,
+    dialect: &SQLDialect,
+) -> Result<(), QueryError> {
+    let SQLDialect::MySQL = dialect else {
+        return Ok(());
+    };
+
+    let mut dollars: Vec<(Location, String)> = collect_placeholder_locations(statement)
+        .into_iter()
+        .filter(|(_, placeholder)| placeholder_kind(placeholder) == PlaceholderKind::Dollar)
+        .collect();
+    // MySQL allows `$` in identifiers, so `$1` parses as a column there.
+    let _ = visit_expressions(statement, |expr| {
+        if let Expr::Identifier(ident) = expr {
+            let number = ident.value.strip_prefix('$').unwrap_or_default();
+            if ident.quote_style.is_none()
+                && !number.is_empty()
+                && number.chars().all(|c| c.is_ascii_digit())
+            {
+                dollars.push((ident.span.start, ident.value.clone()));
+            }
+        }
+        ControlFlow::<()>::Continue(())
+    });
+    dollars.sort_by_key(|(location, _)| *location);
+
+    match dollars.into_iter().next() {
+        Some((location, placeholder)) => Err(QueryError::UnsupportedPlaceholder {
+            placeholder,
+            location,
+            dialect: "MySQL".to_string(),
+        }),
+        None => Ok(()),
+    }
+}
+
+/// Check that `@slice` parameters only appear as items of `IN (...)` lists,
+/// the one place a run of bind markers is valid SQL.
+fn check_slice_placements(
+    statement: &Statement,
+    placeholders: &[(Location, String)],
+    fields: &mut [QueryInputField],
+) -> Result<(), QueryError> {
+    // Each list item, whether its list is negated and whether it is the
+    // list's only item.
+    let mut list_items: HashMap<Location, (bool, bool)> = HashMap::new();
+    let _ = visit_expressions(statement, |expr| {
+        if let Expr::InList { list, negated, .. } = expr {
+            for location in list.iter().filter_map(placeholder_location) {
+                list_items.insert(location, (*negated, list.len() == 1));
+            }
+        }
+        ControlFlow::<()>::Continue(())
+    });
+
+    let invalid = |placeholder: &str, reason: &str| QueryError::InvalidParamDirective {
+        name: placeholder.to_string(),
+        reason: reason.to_string(),
+    };
+    let mut placed: HashMap<String, bool> = HashMap::new();
+    let mut anon_counter = 0usize;
+    for (location, placeholder) in placeholders {
+        let name = placeholder_field_name(placeholder, &mut anon_counter);
+        let is_slice = fields
+            .iter()
+            .any(|f| f.placeholder_name == name && f.slice.is_some());
+        if !is_slice {
+            continue;
+        }
+        let Some(&(negated, only_item)) = list_items.get(location) else {
+            return Err(invalid(
+                placeholder,
+                "a @slice parameter can only be used as an item of an IN (...) list",
+            ));
+        };
+        // An empty list expands to a predicate that is true for every row,
+        // which only works when the slice is the whole list.
+        if negated && !only_item {
+            return Err(invalid(
+                placeholder,
+                "a @slice parameter must be the only item of a NOT IN (...) list",
+            ));
+        }
+        if placed
+            .insert(name, negated)
+            .is_some_and(|was| was != negated)
+        {
+            return Err(invalid(
+                placeholder,
+                "a @slice parameter can't be used in both IN (...) and NOT IN (...) lists",
+            ));
+        }
+    }
+
+    for field in fields.iter_mut() {
+        if let Some(SliceBinding::Expanded { negated }) = &mut field.slice {
+            *negated = placed
+                .get(&field.placeholder_name)
+                .copied()
+                .unwrap_or(false);
+        }
+    }
+
+    Ok(())
+}
+
+/// Bind `@slice` parameters as arrays instead of expanding them, for
+/// PostgreSQL: `x IN (:ids)` becomes `x = ANY(:ids)` and `x NOT IN (:ids)`
+/// becomes `x <> ALL(:ids)`.
+pub fn bind_slices_as_arrays(result: &mut QueryParseResult) -> Result<(), QueryError> {
+    if result.input_fields.iter().all(|f| f.slice.is_none()) {
+        return Ok(());
+    }
+
+    let names = placeholder_names(&result.statement);
+    let is_slice = |location: Location| {
+        names.get(&location).is_some_and(|name| {
+            result
+                .input_fields
+                .iter()
+                .any(|f| &f.placeholder_name == name && f.slice.is_some())
+        })
+    };
+
+    let mut error: Option<QueryError> = None;
+    let _ = visit_expressions_mut(&mut result.statement, |expr| {
+        let Expr::InList {
+            expr: left,
+            list,
+            negated,
+        } = expr
+        else {
+            return ControlFlow::Continue(());
+        };
+        let slices: Vec<&Expr> = list
+            .iter()
+            .filter(|item| placeholder_location(item).is_some_and(is_slice))
+            .collect();
+        if slices.is_empty() {
+            return ControlFlow::Continue(());
+        }
+        if list.len() > 1 {
+            error = Some(QueryError::InvalidParamDirective {
+                name: slices[0].to_string(),
+                reason: "on PostgreSQL a @slice parameter must be the only item of its \
+                         IN (...) list"
+                    .to_string(),
+            });
+            return ControlFlow::Break(());
+        }
+        let left = left.clone();
+        let right = Box::new(list[0].clone());
+        *expr = if *negated {
+            Expr::AllOp {
+                left,
+                compare_op: BinaryOperator::NotEq,
+                right,
+            }
+        } else {
+            Expr::AnyOp {
+                left,
+                compare_op: BinaryOperator::Eq,
+                right,
+                is_some: false,
+            }
+        };
+        ControlFlow::Continue(())
+    });
+    if let Some(error) = error {
+        return Err(error);
+    }
+
+    for field in &mut result.input_fields {
+        if field.slice.is_some() {
+            field.slice = Some(SliceBinding::Array);
+        }
+    }
+    Ok(())
+}
+
+/// The SQL type for the type given in an `@param` directive. Python type
+/// names are accepted alongside SQL types; `None` when the type isn't
+/// recognised.
+fn directive_sql_type(data_type: &str) -> Option<String> {
+    let python_type = match data_type {
+        "int" => Some("INTEGER"),
+        "str" => Some("TEXT"),
+        "float" => Some("REAL"),
+        "bool" => Some("BOOLEAN"),
+        "bytes" => Some("BLOB"),
+        "decimal.Decimal" | "Decimal" => Some("DECIMAL"),
+        "datetime.datetime" | "datetime" => Some("TIMESTAMP"),
+        "datetime.date" | "date" => Some("DATE"),
+        "datetime.time" | "time" => Some("TIME"),
+        "uuid.UUID" | "UUID" => Some("UUID"),
+        _ => None,
+    };
+    match python_type {
+        Some(sql_type) => Some(sql_type.to_string()),
+        None if classify_sql_type(data_type) != SqlTypeClass::Unknown => {
+            Some(data_type.to_string())
+        }
+        None => None,
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Tests
+// ---------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
     use super::*;
-    use std::collections::HashMap;
+
+    fn table(name: &str, columns: Vec<Column>) -> Table {
+        Table {
+            name: name.to_string(),
+            kind: TableKind::Table,
+            schema: None,
+            columns,
+            primary_key: vec![],
+            constraints: vec![],
+        }
+    }
+
+    fn column(name: &str, data_type: &str, nullable: bool) -> Column {
+        Column {
+            name: name.to_string(),
+            data_type: data_type.to_string(),
+            nullable,
+        }
+    }
 
     #[test]
     fn test_sql_type_to_python_integer() {
@@ -769,6 +2444,9 @@ mod tests {
         assert_eq!(sql_type_to_python("BIGINT"), "int");
         assert_eq!(sql_type_to_python("SMALLINT"), "int");
         assert_eq!(sql_type_to_python("TINYINT"), "int");
+        assert_eq!(sql_type_to_python("SERIAL"), "int");
+        assert_eq!(sql_type_to_python("BIGSERIAL"), "int");
+        assert_eq!(sql_type_to_python("SMALLSERIAL"), "int");
     }
 
     #[test]
@@ -847,10 +2525,7 @@ mod tests {
         let ast = SQLParser::parse_sql(&SQLiteDialect {}, sql).unwrap();
         if let sqlparser::ast::Statement::Query(q) = &ast[0] {
             let select = q.body.as_select().unwrap();
-            let mut placeholders = Vec::new();
-            if let Some(expr) = &select.selection {
-                collect_placeholders(expr, &mut placeholders);
-            }
+            let placeholders = collect_placeholders(select.selection.as_ref().unwrap());
             assert_eq!(placeholders, vec![":id", ":email"]);
         } else {
             panic!("expected Query");
@@ -858,10 +2533,56 @@ mod tests {
     }
 
     #[test]
-    fn test_build_input_fields_named_dedup() {
+    fn test_collect_placeholders_whole_statement() {
+        use sqlparser::dialect::SQLiteDialect;
+        use sqlparser::parser::Parser as SQLParser;
+
+        let sql = "SELECT CAST(:scale AS REAL) * id FROM users u \
+                   JOIN orders o ON o.user_id = u.id AND o.status = :status \
+                   WHERE EXISTS (SELECT 1 FROM orders WHERE total > :min_total) \
+                   AND u.id IN (SELECT user_id FROM orders WHERE status = (:other)) \
+                   AND (u.id, u.email) = (:id, :email) \
+                   ORDER BY u.id + :offset_by LIMIT :limit OFFSET :offset";
+        let ast = SQLParser::parse_sql(&SQLiteDialect {}, sql).unwrap();
+        assert_eq!(
+            collect_placeholders(&ast[0]),
+            vec![
+                ":scale",
+                ":status",
+                ":min_total",
+                ":other",
+                ":id",
+                ":email",
+                ":offset_by",
+                ":limit",
+                ":offset"
+            ]
+        );
+
+        // `?` markers are numbered by where they appear in the text.
+        let sql = "WITH recent AS (SELECT id FROM users WHERE id > ?) \
+                   SELECT id FROM recent WHERE id < ? LIMIT ?";
+        let ast = SQLParser::parse_sql(&SQLiteDialect {}, sql).unwrap();
         let schema = SchemaParseResult {
-            table_fields: HashMap::new(),
+            tables: vec![table("users", vec![column("id", "INTEGER", false)])],
+        };
+        let annotation = QueryAnnotation {
+            name: "recent".to_string(),
+            cardinality: QueryCardinality::Many,
+            params: Vec::new(),
         };
+        let result = process_sql_statement(&ast[0], annotation, &schema).unwrap();
+        let names: Vec<&str> = result
+            .input_fields
+            .iter()
+            .map(|f| f.name.as_str())
+            .collect();
+        assert_eq!(names, vec!["p1", "p2", "p3"]);
+    }
+
+    #[test]
+    fn test_build_input_fields_named_dedup() {
+        let schema = SchemaParseResult { tables: vec![] };
         let raw = vec![":id".to_string(), ":email".to_string(), ":id".to_string()];
         let fields = build_input_fields(&raw, &[], &schema);
         assert_eq!(fields.len(), 2);
@@ -871,9 +2592,7 @@ mod tests {
 
     #[test]
     fn test_build_input_fields_anon_positional() {
-        let schema = SchemaParseResult {
-            table_fields: HashMap::new(),
-        };
+        let schema = SchemaParseResult { tables: vec![] };
         let raw = vec!["?".to_string(), "?".to_string(), "?".to_string()];
         let fields = build_input_fields(&raw, &[], &schema);
         assert_eq!(fields.len(), 3);
@@ -884,13 +2603,13 @@ mod tests {
 
     #[test]
     fn test_build_input_fields_type_inference() {
-        let mut users_cols = HashMap::new();
-        users_cols.insert("id".to_string(), "INTEGER".to_string());
-        users_cols.insert("email".to_string(), "TEXT".to_string());
-        let mut table_fields = HashMap::new();
-        table_fields.insert("users".to_string(), users_cols);
-
-        let schema = SchemaParseResult { table_fields };
+        let users_cols = vec![
+            column("id", "INTEGER", false),
+            column("email", "TEXT", false),
+        ];
+        let schema = SchemaParseResult {
+            tables: vec![table("users", users_cols)],
+        };
         let raw = vec![":id".to_string(), ":email".to_string()];
         let fields = build_input_fields(&raw, &["users"], &schema);
         assert_eq!(fields[0].name, "id");
@@ -898,4 +2617,1019 @@ mod tests {
         assert_eq!(fields[1].name, "email");
         assert_eq!(fields[1].data_type, "str");
     }
+
+    #[test]
+    fn test_process_sql_statement_output_types() {
+        use sqlparser::dialect::SQLiteDialect;
+        use sqlparser::parser::Parser as SQLParser;
+
+        let users_cols = vec![
+            column("id", "INTEGER", false),
+            column("email", "TEXT", false),
+            column("created_at", "TIMESTAMP", true),
+        ];
+        let schema = SchemaParseResult {
+            tables: vec![table("users", users_cols)],
+        };
+
+        let sql = "SELECT id AS user_id, u.email, created_at FROM users u WHERE id = :id";
+        let ast = SQLParser::parse_sql(&SQLiteDialect {}, sql).unwrap();
+        let annotation = QueryAnnotation {
+            name: "get_user".to_string(),
+            cardinality: QueryCardinality::One,
+            params: Vec::new(),
+        };
+        let result = process_sql_statement(&ast[0], annotation, &schema).unwrap();
+
+        let fields: Vec<(&str, &str)> = result
+            .output_fields
+            .iter()
+            .map(|f| (f.name.as_str(), f.python_type.as_str()))
+            .collect();
+        assert_eq!(
+            fields,
+            vec![
+                ("user_id", "int"),
+                ("email", "str"),
+                ("created_at", "datetime.datetime")
+            ]
+        );
+        assert_eq!(result.output_fields[1].sql_type.as_deref(), Some("TEXT"));
+        assert!(!result.output_fields[1].nullable);
+        assert!(result.output_fields[2].nullable);
+    }
+
+    #[test]
+    fn test_process_sql_statement_quoted_aliases() {
+        use sqlparser::dialect::SQLiteDialect;
+        use sqlparser::parser::Parser as SQLParser;
+
+        let schema = SchemaParseResult {
+            tables: vec![table("users", vec![column("id", "INTEGER", false)])],
+        };
+
+        let sql = "SELECT id AS \"userId\", COUNT(*) AS \"user count\" FROM users GROUP BY id";
+        let ast = SQLParser::parse_sql(&SQLiteDialect {}, sql).unwrap();
+        let annotation = QueryAnnotation {
+            name: "count_users".to_string(),
+            cardinality: QueryCardinality::Many,
+            params: Vec::new(),
+        };
+        let result = process_sql_statement(&ast[0], annotation, &schema).unwrap();
+
+        let names: Vec<&str> = result
+            .output_fields
+            .iter()
+            .map(|f| f.name.as_str())
+            .collect();
+        assert_eq!(names, vec!["userId", "user count"]);
+    }
+
+    #[test]
+    fn test_process_sql_statement_scalar_column_count() {
+        use sqlparser::dialect::SQLiteDialect;
+        use sqlparser::parser::Parser as SQLParser;
+
+        let schema = SchemaParseResult {
+            tables: vec![table(
+                "users",
+                vec![
+                    column("id", "INTEGER", false),
+                    column("email", "TEXT", false),
+                ],
+            )],
+        };
+        let process = |sql: &str, cardinality: QueryCardinality| {
+            let ast = SQLParser::parse_sql(&SQLiteDialect {}, sql).unwrap();
+            let annotation = QueryAnnotation {
+                name: "q".to_string(),
+                cardinality,
+                params: Vec::new(),
+            };
+            process_sql_statement(&ast[0], annotation, &schema)
+        };
+
+        let result = process(
+            "SELECT email FROM users WHERE id = :id",
+            QueryCardinality::Val,
+        )
+        .unwrap();
+        assert_eq!(result.output_fields.len(), 1);
+        assert!(process("SELECT id FROM users", QueryCardinality::Column).is_ok());
+
+        match process("SELECT id, email FROM users", QueryCardinality::Column) {
+            Err(QueryError::ScalarColumnCount { cardinality, found }) => {
+                assert_eq!(cardinality, ":column");
+                assert_eq!(found, 2);
+            }
+            other => panic!("expected ScalarColumnCount, got {:?}", other.map(|_| ())),
+        }
+        assert!(matches!(
+            process("DELETE FROM users WHERE id = :id", QueryCardinality::Val),
+            Err(QueryError::ScalarColumnCount { found: 0, .. })
+        ));
+    }
+
+    #[test]
+    fn test_process_sql_statement_nullability() {
+        use sqlparser::dialect::SQLiteDialect;
+        use sqlparser::parser::Parser as SQLParser;
+
+        let schema_sql = "
+            CREATE TABLE users (id INTEGER PRIMARY KEY, email TEXT NOT NULL, bio TEXT);
+            CREATE TABLE orders (
+                order_id INTEGER,
+                user_id INTEGER NOT NULL,
+                PRIMARY KEY (order_id)
+            );
+        ";
+        let mut schema = SchemaParseResult::default();
+        schema.apply_sql(schema_sql, &SQLiteDialect {}).unwrap();
+
+        let sql = "SELECT u.id, u.email, u.bio, o.order_id FROM users u \
+                   LEFT JOIN orders o ON o.user_id = u.id WHERE u.bio = :bio";
+        let ast = SQLParser::parse_sql(&SQLiteDialect {}, sql).unwrap();
+        let annotation = QueryAnnotation {
+            name: "list_users".to_string(),
+            cardinality: QueryCardinality::Many,
+            params: Vec::new(),
+        };
+        let result = process_sql_statement(&ast[0], annotation, &schema).unwrap();
+
+        let nullability: Vec<(&str, bool)> = result
+            .output_fields
+            .iter()
+            .map(|f| (f.name.as_str(), f.nullable))
+            .collect();
+        assert_eq!(
+            nullability,
+            vec![
+                ("id", false),
+                ("email", false),
+                ("bio", true),
+                ("order_id", true)
+            ]
+        );
+        assert_eq!(result.input_fields[0].name, "bio");
+        assert!(result.input_fields[0].nullable);
+    }
+
+    #[test]
+    fn test_process_sql_statement_outer_join_nullability_by_alias() {
+        use sqlparser::dialect::SQLiteDialect;
+        use sqlparser::parser::Parser as SQLParser;
+
+        let schema_sql = "
+            CREATE TABLE users (id INTEGER PRIMARY KEY, email TEXT NOT NULL, manager_id INTEGER);
+            CREATE TABLE tags (id INTEGER PRIMARY KEY, user_id INTEGER, label TEXT NOT NULL);
+        ";
+        let mut schema = SchemaParseResult::default();
+        schema.apply_sql(schema_sql, &SQLiteDialect {}).unwrap();
+        let nullability = |sql: &str| -> Vec<(String, bool)> {
+            let ast = SQLParser::parse_sql(&SQLiteDialect {}, sql).unwrap();
+            let annotation = QueryAnnotation {
+                name: "query".to_string(),
+                cardinality: QueryCardinality::Many,
+                params: Vec::new(),
+            };
+            process_sql_statement(&ast[0], annotation, &schema)
+                .unwrap()
+                .output_fields
+                .iter()
+                .map(|f| (f.name.clone(), f.nullable))
+                .collect()
+        };
+
+        // Only the optional side of a self-join is nullable.
+        assert_eq!(
+            nullability(
+                "SELECT e.email, m.email AS manager_email FROM users e \
+                 LEFT JOIN users m ON m.id = e.manager_id"
+            ),
+            vec![
+                ("email".to_string(), false),
+                ("manager_email".to_string(), true)
+            ]
+        );
+        // A table joined both outer and inner is nullable only through the
+        // outer relation.
+        assert_eq!(
+            nullability(
+                "SELECT t.label, t2.label AS other_label FROM users u \
+                 LEFT JOIN tags t ON t.user_id = u.id \
+                 JOIN tags t2 ON t2.user_id = u.id"
+            ),
+            vec![
+                ("label".to_string(), true),
+                ("other_label".to_string(), false)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_process_sql_statement_wildcard_expansion() {
+        use sqlparser::dialect::SQLiteDialect;
+        use sqlparser::parser::Parser as SQLParser;
+
+        let schema_sql = "
+            CREATE TABLE users (id INTEGER PRIMARY KEY, email TEXT NOT NULL, bio TEXT);
+            CREATE TABLE orders (order_id INTEGER PRIMARY KEY, user_id INTEGER NOT NULL);
+        ";
+        let mut schema = SchemaParseResult::default();
+        schema.apply_sql(schema_sql, &SQLiteDialect {}).unwrap();
+        let annotation = QueryAnnotation {
+            name: "list".to_string(),
+            cardinality: QueryCardinality::Many,
+            params: Vec::new(),
+        };
+
+        let ast = SQLParser::parse_sql(&SQLiteDialect {}, "SELECT * FROM users").unwrap();
+        let result = process_sql_statement(&ast[0], annotation.clone(), &schema).unwrap();
+        let names: Vec<&str> = result
+            .output_fields
+            .iter()
+            .map(|f| f.name.as_str())
+            .collect();
+        assert_eq!(names, vec!["id", "email", "bio"]);
+
+        let sql = "SELECT o.*, u.email FROM users u LEFT JOIN orders o ON o.user_id = u.id";
+        let ast = SQLParser::parse_sql(&SQLiteDialect {}, sql).unwrap();
+        let result = process_sql_statement(&ast[0], annotation.clone(), &schema).unwrap();
+        let fields: Vec<(&str, Option<&str>, bool)> = result
+            .output_fields
+            .iter()
+            .map(|f| (f.name.as_str(), f.source.table.as_deref(), f.nullable))
+            .collect();
+        assert_eq!(
+            fields,
+            vec![
+                ("order_id", Some("orders"), true),
+                ("user_id", Some("orders"), true),
+                ("email", Some("users"), false)
+            ]
+        );
+
+        let ast = SQLParser::parse_sql(&SQLiteDialect {}, "SELECT x.* FROM users").unwrap();
+        assert!(process_sql_statement(&ast[0], annotation, &schema).is_err());
+    }
+
+    #[test]
+    fn test_process_sql_statement_quoted_table_names() {
+        use sqlparser::dialect::PostgreSqlDialect;
+        use sqlparser::parser::Parser as SQLParser;
+
+        let schema_sql = "CREATE TABLE \"Users\" (id INTEGER PRIMARY KEY, email TEXT NOT NULL);";
+        let mut schema = SchemaParseResult::default();
+        schema.apply_sql(schema_sql, &PostgreSqlDialect {}).unwrap();
+
+        let process = |sql: &str| {
+            let ast = SQLParser::parse_sql(&PostgreSqlDialect {}, sql).unwrap();
+            let annotation = QueryAnnotation {
+                name: "query".to_string(),
+                cardinality: QueryCardinality::Many,
+                params: Vec::new(),
+            };
+            process_sql_statement(&ast[0], annotation, &schema).unwrap()
+        };
+        let output_types = |result: &QueryParseResult| -> Vec<(String, String)> {
+            result
+                .output_fields
+                .iter()
+                .map(|f| (f.name.clone(), f.python_type.clone()))
+                .collect()
+        };
+        let expected = vec![
+            ("id".to_string(), "int".to_string()),
+            ("email".to_string(), "str".to_string()),
+        ];
+
+        let result = process("SELECT id, \"Users\".email FROM \"Users\"");
+        assert_eq!(output_types(&result), expected);
+
+        let result = process("SELECT u.* FROM \"Users\" u WHERE u.id = $1");
+        assert_eq!(output_types(&result), expected);
+        assert_eq!(result.input_fields[0].data_type, "int");
+
+        let result = process("UPDATE \"Users\" SET email = $1 WHERE id = $2");
+        let inputs: Vec<&str> = result
+            .input_fields
+            .iter()
+            .map(|f| f.data_type.as_str())
+            .collect();
+        assert_eq!(inputs, vec!["str", "int"]);
+    }
+
+    #[test]
+    fn test_process_sql_statement_qualified_wildcards_shared_columns() {
+        use sqlparser::dialect::SQLiteDialect;
+        use sqlparser::parser::Parser as SQLParser;
+
+        let schema_sql = "
+            CREATE TABLE users (id INTEGER PRIMARY KEY, email TEXT NOT NULL);
+            CREATE TABLE orders (id INTEGER PRIMARY KEY, user_id INTEGER NOT NULL);
+        ";
+        let mut schema = SchemaParseResult::default();
+        schema.apply_sql(schema_sql, &SQLiteDialect {}).unwrap();
+        let annotation = QueryAnnotation {
+            name: "users_with_orders".to_string(),
+            cardinality: QueryCardinality::Many,
+            params: Vec::new(),
+        };
+
+        let sql = "SELECT u.*, o.* FROM users u JOIN orders o ON o.user_id = u.id";
+        let ast = SQLParser::parse_sql(&SQLiteDialect {}, sql).unwrap();
+        let result = process_sql_statement(&ast[0], annotation, &schema).unwrap();
+        let names: Vec<&str> = result
+            .output_fields
+            .iter()
+            .map(|f| f.name.as_str())
+            .collect();
+        assert_eq!(names, vec!["id", "email", "id_2", "user_id"]);
+    }
+
+    #[test]
+    fn test_process_sql_statement_wildcard_self_join() {
+        use sqlparser::dialect::SQLiteDialect;
+        use sqlparser::parser::Parser as SQLParser;
+
+        let schema_sql = "
+            CREATE TABLE employees (id INTEGER PRIMARY KEY, name TEXT NOT NULL, manager_id INTEGER);
+        ";
+        let mut schema = SchemaParseResult::default();
+        schema.apply_sql(schema_sql, &SQLiteDialect {}).unwrap();
+        let annotation = QueryAnnotation {
+            name: "with_managers".to_string(),
+            cardinality: QueryCardinality::Many,
+            params: Vec::new(),
+        };
+
+        let sql = "SELECT * FROM employees e JOIN employees m ON e.manager_id = m.id";
+        let ast = SQLParser::parse_sql(&SQLiteDialect {}, sql).unwrap();
+        let result = process_sql_statement(&ast[0], annotation, &schema).unwrap();
+        let fields: Vec<(&str, &str, bool)> = result
+            .output_fields
+            .iter()
+            .map(|f| (f.name.as_str(), f.python_type.as_str(), f.nullable))
+            .collect();
+        assert_eq!(
+            fields,
+            vec![
+                ("id", "int", false),
+                ("name", "str", false),
+                ("manager_id", "int", true),
+                ("id_2", "int", false),
+                ("name_2", "str", false),
+                ("manager_id_2", "int", true),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_process_sql_statement_returning() {
+        use sqlparser::dialect::PostgreSqlDialect;
+        use sqlparser::parser::Parser as SQLParser;
+
+        let schema_sql = "
+            CREATE TABLE users (id INTEGER PRIMARY KEY, email TEXT NOT NULL, created_at TIMESTAMP);
+        ";
+        let mut schema = SchemaParseResult::default();
+        schema.apply_sql(schema_sql, &PostgreSqlDialect {}).unwrap();
+        let annotation = QueryAnnotation {
+            name: "write".to_string(),
+            cardinality: QueryCardinality::One,
+            params: Vec::new(),
+        };
+        let output = |sql: &str| -> Vec<(String, String, bool)> {
+            let ast = SQLParser::parse_sql(&PostgreSqlDialect {}, sql).unwrap();
+            process_sql_statement(&ast[0], annotation.clone(), &schema)
+                .unwrap()
+                .output_fields
+                .iter()
+                .map(|f| (f.name.clone(), f.python_type.clone(), f.nullable))
+                .collect()
+        };
+
+        assert_eq!(
+            output("INSERT INTO users (email) VALUES (:email) RETURNING id, created_at AS at"),
+            vec![
+                ("id".to_string(), "int".to_string(), false),
+                ("at".to_string(), "datetime.datetime".to_string(), true)
+            ]
+        );
+        assert_eq!(
+            output("UPDATE users u SET email = :email WHERE u.id = :id RETURNING u.email"),
+            vec![("email".to_string(), "str".to_string(), false)]
+        );
+        let names: Vec<String> = output("DELETE FROM users WHERE id = :id RETURNING *")
+            .into_iter()
+            .map(|(name, _, _)| name)
+            .collect();
+        assert_eq!(names, vec!["id", "email", "created_at"]);
+    }
+
+    #[test]
+    fn test_process_sql_statement_cte() {
+        use sqlparser::dialect::PostgreSqlDialect;
+        use sqlparser::parser::Parser as SQLParser;
+
+        let schema_sql = "
+            CREATE TABLE users (id INTEGER PRIMARY KEY, email TEXT NOT NULL);
+            CREATE TABLE orders (order_id INTEGER PRIMARY KEY, user_id INTEGER NOT NULL, total REAL);
+            CREATE TABLE employees (id INTEGER PRIMARY KEY, manager_id INTEGER);
+        ";
+        let mut schema = SchemaParseResult::default();
+        schema.apply_sql(schema_sql, &PostgreSqlDialect {}).unwrap();
+        let annotation = QueryAnnotation {
+            name: "report".to_string(),
+            cardinality: QueryCardinality::Many,
+            params: Vec::new(),
+        };
+
+        let sql = "WITH big_orders (buyer, amount) AS (
+                       SELECT user_id, total FROM orders WHERE total > :min_total
+                   ),
+                   buyers AS (SELECT b.buyer FROM big_orders b)
+                   SELECT u.email, bo.amount FROM users u
+                   JOIN big_orders bo ON bo.buyer = u.id
+                   WHERE u.id IN (SELECT buyer FROM buyers) AND u.email = :email";
+        let ast = SQLParser::parse_sql(&PostgreSqlDialect {}, sql).unwrap();
+        let result = process_sql_statement(&ast[0], annotation.clone(), &schema).unwrap();
+        let fields: Vec<(&str, &str, bool)> = result
+            .output_fields
+            .iter()
+            .map(|f| (f.name.as_str(), f.python_type.as_str(), f.nullable))
+            .collect();
+        assert_eq!(
+            fields,
+            vec![("email", "str", false), ("amount", "float", true)]
+        );
+        let inputs: Vec<(&str, &str)> = result
+            .input_fields
+            .iter()
+            .map(|f| (f.name.as_str(), f.data_type.as_str()))
+            .collect();
+        assert_eq!(inputs, vec![("min_total", "float"), ("email", "str")]);
+
+        let sql = "WITH RECURSIVE reports AS (
+                       SELECT id, manager_id FROM employees WHERE id = :root
+                       UNION ALL
+                       SELECT e.id, e.manager_id FROM employees e
+                       JOIN reports r ON e.manager_id = r.id
+                   )
+                   SELECT * FROM reports";
+        let ast = SQLParser::parse_sql(&PostgreSqlDialect {}, sql).unwrap();
+        let result = process_sql_statement(&ast[0], annotation.clone(), &schema).unwrap();
+        let fields: Vec<(&str, &str)> = result
+            .output_fields
+            .iter()
+            .map(|f| (f.name.as_str(), f.python_type.as_str()))
+            .collect();
+        assert_eq!(fields, vec![("id", "int"), ("manager_id", "int")]);
+        assert_eq!(result.input_fields.len(), 1);
+        assert_eq!(result.input_fields[0].name, "root");
+        assert_eq!(result.input_fields[0].data_type, "int");
+
+        let sql = "WITH t (a, b) AS (SELECT id FROM users) SELECT a FROM t";
+        let ast = SQLParser::parse_sql(&PostgreSqlDialect {}, sql).unwrap();
+        assert!(matches!(
+            process_sql_statement(&ast[0], annotation, &schema),
+            Err(QueryError::ColumnCountMismatch { .. })
+        ));
+    }
+
+    #[test]
+    fn test_process_sql_statement_set_operations() {
+        use sqlparser::dialect::SQLiteDialect;
+        use sqlparser::parser::Parser as SQLParser;
+
+        let schema_sql = "
+            CREATE TABLE users (id INTEGER PRIMARY KEY, email TEXT NOT NULL);
+            CREATE TABLE admins (admin_id INTEGER PRIMARY KEY, contact TEXT);
+        ";
+        let mut schema = SchemaParseResult::default();
+        schema.apply_sql(schema_sql, &SQLiteDialect {}).unwrap();
+        let annotation = QueryAnnotation {
+            name: "everyone".to_string(),
+            cardinality: QueryCardinality::Many,
+            params: Vec::new(),
+        };
+
+        let sql = "SELECT id, email FROM users WHERE id > ? \
+                   UNION ALL SELECT admin_id, contact FROM admins WHERE admin_id < ? \
+                   EXCEPT SELECT id, email FROM users WHERE email = ?";
+        let ast = SQLParser::parse_sql(&SQLiteDialect {}, sql).unwrap();
+        let result = process_sql_statement(&ast[0], annotation.clone(), &schema).unwrap();
+        let fields: Vec<(&str, &str, bool)> = result
+            .output_fields
+            .iter()
+            .map(|f| (f.name.as_str(), f.python_type.as_str(), f.nullable))
+            .collect();
+        assert_eq!(fields, vec![("id", "int", false), ("email", "str", true)]);
+        let inputs: Vec<&str> = result
+            .input_fields
+            .iter()
+            .map(|f| f.name.as_str())
+            .collect();
+        assert_eq!(inputs, vec!["p1", "p2", "p3"]);
+
+        let sql = "SELECT id, email FROM users UNION SELECT admin_id FROM admins";
+        let ast = SQLParser::parse_sql(&SQLiteDialect {}, sql).unwrap();
+        assert!(matches!(
+            process_sql_statement(&ast[0], annotation.clone(), &schema),
+            Err(QueryError::IncompatibleSetOperation { .. })
+        ));
+
+        let sql = "SELECT email FROM users INTERSECT SELECT admin_id FROM admins";
+        let ast = SQLParser::parse_sql(&SQLiteDialect {}, sql).unwrap();
+        assert!(matches!(
+            process_sql_statement(&ast[0], annotation, &schema),
+            Err(QueryError::IncompatibleSetOperation { .. })
+        ));
+    }
+
+    #[test]
+    fn test_process_sql_statement_computed_expressions() {
+        use sqlparser::dialect::PostgreSqlDialect;
+        use sqlparser::parser::Parser as SQLParser;
+
+        let schema_sql = "
+            CREATE TABLE orders (
+                order_id INTEGER PRIMARY KEY,
+                status TEXT NOT NULL,
+                total NUMERIC(10, 2) NOT NULL,
+                discount REAL,
+                note TEXT
+            );
+        ";
+        let mut schema = SchemaParseResult::default();
+        schema.apply_sql(schema_sql, &PostgreSqlDialect {}).unwrap();
+        let annotation = QueryAnnotation {
+            name: "stats".to_string(),
+            cardinality: QueryCardinality::One,
+            params: Vec::new(),
+        };
+
+        let sql = "SELECT COUNT(*) AS n, COUNT(note), SUM(total), MAX(status), AVG(discount), \
+                   CAST(order_id AS TEXT), total * 2 AS doubled, total - discount AS net, \
+                   COALESCE(note, status) AS label, COALESCE(note, discount), \
+                   CASE WHEN total > 10 THEN 'big' ELSE 'small' END AS size, \
+                   CASE WHEN total > 10 THEN note END, \
+                   order_id = 1 AS is_first, upper(status), my_udf(order_id) \
+                   FROM orders";
+        let ast = SQLParser::parse_sql(&PostgreSqlDialect {}, sql).unwrap();
+        let result = process_sql_statement(&ast[0], annotation, &schema).unwrap();
+        let fields: Vec<(&str, Option<&str>, &str, bool)> = result
+            .output_fields
+            .iter()
+            .map(|f| {
+                (
+                    f.name.as_str(),
+                    f.sql_type.as_deref(),
+                    f.python_type.as_str(),
+                    f.nullable,
+                )
+            })
+            .collect();
+        assert_eq!(
+            fields,
+            vec![
+                ("n", Some("BIGINT"), "int", false),
+                ("count", Some("BIGINT"), "int", false),
+                ("sum", Some("NUMERIC(10,2)"), "Decimal", true),
+                ("max", Some("TEXT"), "str", true),
+                ("avg", Some("REAL"), "float", true),
+                ("order_id", Some("TEXT"), "str", false),
+                ("doubled", Some("NUMERIC(10,2)"), "Decimal", false),
+                ("net", Some("REAL"), "float", true),
+                ("label", Some("TEXT"), "str", false),
+                ("coalesce", Some("TEXT"), "str", true),
+                ("size", Some("TEXT"), "str", false),
+                ("case", Some("TEXT"), "str", true),
+                ("is_first", Some("BOOLEAN"), "bool", false),
+                ("upper", Some("TEXT"), "str", false),
+                ("my_udf", None, "Any", true),
+            ]
+        );
+
+        let sql = "SELECT COUNT(*), COUNT(note), MIN(total) FROM orders";
+        let ast = SQLParser::parse_sql(&PostgreSqlDialect {}, sql).unwrap();
+        let annotation = QueryAnnotation {
+            name: "counts".to_string(),
+            cardinality: QueryCardinality::One,
+            params: Vec::new(),
+        };
+        let result = process_sql_statement(&ast[0], annotation, &schema).unwrap();
+        let names: Vec<&str> = result
+            .output_fields
+            .iter()
+            .map(|f| f.name.as_str())
+            .collect();
+        assert_eq!(names, vec!["count", "count_2", "min"]);
+    }
+
+    #[test]
+    fn test_process_sql_statement_param_context_types() {
+        use sqlparser::dialect::SQLiteDialect;
+        use sqlparser::parser::Parser as SQLParser;
+
+        let schema = SchemaParseResult {
+            tables: vec![
+                table(
+                    "users",
+                    vec![
+                        column("id", "INTEGER", false),
+                        column("email", "TEXT", false),
+                        column("bio", "TEXT", true),
+                    ],
+                ),
+                table(
+                    "orders",
+                    vec![
+                        column("order_id", "INTEGER", false),
+                        column("customer_id", "INTEGER", false),
+                        column("total", "REAL", false),
+                    ],
+                ),
+            ],
+        };
+        let annotation = QueryAnnotation {
+            name: "query".to_string(),
+            cardinality: QueryCardinality::Many,
+            params: Vec::new(),
+        };
+        let input_types = |sql: &str| -> Vec<(String, String, bool)> {
+            let ast = SQLParser::parse_sql(&SQLiteDialect {}, sql).unwrap();
+            process_sql_statement(&ast[0], annotation.clone(), &schema)
+                .unwrap()
+                .input_fields
+                .into_iter()
+                .map(|f| (f.name, f.data_type, f.nullable))
+                .collect()
+        };
+        let expected = |fields: &[(&str, &str, bool)]| -> Vec<(String, String, bool)> {
+            fields
+                .iter()
+                .map(|(name, ty, nullable)| (name.to_string(), ty.to_string(), *nullable))
+                .collect()
+        };
+
+        // Comparisons, through aliases and into correlated subqueries
+        assert_eq!(
+            input_types(
+                "SELECT u.email FROM users u \
+                 WHERE u.id = :user_id AND EXISTS ( \
+                     SELECT 1 FROM orders o WHERE o.customer_id = u.id AND :min < o.total \
+                 ) LIMIT :n"
+            ),
+            expected(&[
+                ("user_id", "int", false),
+                ("min", "float", false),
+                ("n", "int", false)
+            ])
+        );
+
+        // Anonymous and numbered placeholders
+        assert_eq!(
+            input_types("SELECT id FROM users WHERE email = ? AND id BETWEEN ? AND ?"),
+            expected(&[
+                ("p1", "str", false),
+                ("p2", "int", false),
+                ("p3", "int", false)
+            ])
+        );
+        assert_eq!(
+            input_types("SELECT id FROM users WHERE id IN ($1, $2) AND bio LIKE $3"),
+            expected(&[
+                ("p1", "int", false),
+                ("p2", "int", false),
+                ("p3", "str", true)
+            ])
+        );
+
+        // INSERT values line up with the column list, or the table's columns
+        assert_eq!(
+            input_types("INSERT INTO users (bio, email) VALUES (:about, :mail)"),
+            expected(&[("about", "str", true), ("mail", "str", false)])
+        );
+        assert_eq!(
+            input_types("INSERT INTO orders VALUES (?, ?, ?)"),
+            expected(&[
+                ("p1", "int", false),
+                ("p2", "int", false),
+                ("p3", "float", false)
+            ])
+        );
+
+        // UPDATE assignments take the type of the assigned column
+        assert_eq!(
+            input_types("UPDATE users SET bio = ? WHERE id = ?"),
+            expected(&[("p1", "str", true), ("p2", "int", false)])
+        );
+
+        // Without context, named parameters still fall back to their name
+        assert_eq!(
+            input_types("SELECT id FROM users WHERE length(email) > 3 OR email = lower(:email)"),
+            expected(&[("email", "str", false)])
+        );
+    }
+
+    #[test]
+    fn test_extract_query_annotations_param_directives() {
+        let sql = "-- find_orders :many
+                   -- Orders for one customer.
+                   -- @param p1 as customer_id int
+                   -- @param since datetime.datetime?
+                   -- @param label ?
+                   SELECT * FROM orders WHERE customer_id = ? AND created_at > :since;
+
+                   -- @param ignored text
+                   SELECT 1;
+
+                   -- count_orders :one
+                   SELECT COUNT(*) FROM orders;";
+        let annotations = extract_query_annotations(sql);
+        assert_eq!(annotations.len(), 3);
+
+        let find_orders = annotations[0].as_ref().unwrap();
+        assert_eq!(
+            find_orders.params,
+            vec![
+                ParamDirective {
+                    name: "p1".to_string(),
+                    rename: Some("customer_id".to_string()),
+                    data_type: Some("int".to_string()),
+                    nullable: false,
+                    slice: false,
+                },
+                ParamDirective {
+                    name: "since".to_string(),
+                    rename: None,
+                    data_type: Some("datetime.datetime".to_string()),
+                    nullable: true,
+                    slice: false,
+                },
+                ParamDirective {
+                    name: "label".to_string(),
+                    rename: None,
+                    data_type: None,
+                    nullable: true,
+                    slice: false,
+                },
+            ]
+        );
+        assert!(annotations[1].is_none());
+        assert!(annotations[2].as_ref().unwrap().params.is_empty());
+    }
+
+    #[test]
+    fn test_extract_query_annotations_exactly_one() {
+        let sql = "-- get_order :one!
+                   SELECT * FROM orders WHERE id = :id;
+
+                   -- find_order :one
+                   SELECT * FROM orders WHERE id = :id;";
+        let annotations = extract_query_annotations(sql);
+        assert_eq!(annotations.len(), 2);
+
+        let get_order = annotations[0].as_ref().unwrap();
+        assert_eq!(get_order.name, "get_order");
+        assert!(matches!(
+            get_order.cardinality,
+            QueryCardinality::ExactlyOne
+        ));
+        assert_eq!(get_order.cardinality.to_string(), ":one!");
+
+        let find_order = annotations[1].as_ref().unwrap();
+        assert_eq!(find_order.name, "find_order");
+        assert!(matches!(find_order.cardinality, QueryCardinality::One));
+    }
+
+    #[test]
+    fn test_process_sql_statement_param_directives() {
+        use sqlparser::dialect::SQLiteDialect;
+        use sqlparser::parser::Parser as SQLParser;
+
+        let schema = SchemaParseResult {
+            tables: vec![table(
+                "orders",
+                vec![
+                    column("order_id", "INTEGER", false),
+                    column("customer_id", "INTEGER", false),
+                    column("note", "TEXT", true),
+                ],
+            )],
+        };
+        let process = |sql: &str, directives: &str| {
+            let ast = SQLParser::parse_sql(&SQLiteDialect {}, sql).unwrap();
+            let annotation =
+                extract_query_annotations(&format!("-- q :many\n{}\n{}", directives, sql))
+                    .remove(0)
+                    .unwrap();
+            process_sql_statement(&ast[0], annotation, &schema)
+        };
+
+        let fields = |sql: &str, directives: &str| -> Vec<(String, String, String, bool)> {
+            process(sql, directives)
+                .unwrap()
+                .input_fields
+                .into_iter()
+                .map(|f| (f.name, f.placeholder_name, f.data_type, f.nullable))
+                .collect()
+        };
+        let expected =
+            |fields: &[(&str, &str, &str, bool)]| -> Vec<(String, String, String, bool)> {
+                fields
+                    .iter()
+                    .map(|(n, p, t, nullable)| {
+                        (n.to_string(), p.to_string(), t.to_string(), *nullable)
+                    })
+                    .collect()
+            };
+        assert_eq!(
+            fields(
+                "SELECT order_id FROM orders WHERE customer_id = $1 AND note LIKE $2",
+                "-- @param p1 as cid\n-- @param $2 as pattern text",
+            ),
+            expected(&[("cid", "p1", "int", false), ("pattern", "p2", "str", false)])
+        );
+        assert_eq!(
+            fields(
+                "SELECT order_id FROM orders WHERE customer_id = ? OR note = ?",
+                "-- @param p2 as memo",
+            ),
+            expected(&[("p1", "p1", "int", false), ("memo", "p2", "str", true)])
+        );
+        assert_eq!(
+            fields(
+                "SELECT order_id FROM orders WHERE :since < 5",
+                "-- @param :since as since_ts datetime?",
+            ),
+            expected(&[("since_ts", "since", "datetime.datetime", true)])
+        );
+
+        let error = |directives: &str| match process(
+            "SELECT order_id FROM orders WHERE customer_id = :cid AND note = :note",
+            directives,
+        ) {
+            Err(QueryError::InvalidParamDirective { reason, .. }) => reason,
+            other => panic!("expected an invalid directive, got {:?}", other.map(|_| ())),
+        };
+        assert_eq!(
+            error("-- @param missing int"),
+            "the query has no such parameter"
+        );
+        assert_eq!(error("-- @param cid strng"), "unrecognised type \"strng\"");
+        assert_eq!(
+            error("-- @param cid as 1cid"),
+            "\"1cid\" is not a valid parameter name"
+        );
+        assert_eq!(
+            error("-- @param cid as note"),
+            "another parameter is already named \"note\""
+        );
+    }
+
+    #[test]
+    fn test_process_sql_statement_placeholder_styles() {
+        use sqlparser::dialect::{MySqlDialect, SQLiteDialect};
+        use sqlparser::parser::Parser as SQLParser;
+
+        let schema = SchemaParseResult {
+            tables: vec![table(
+                "users",
+                vec![
+                    column("id", "INTEGER", false),
+                    column("email", "TEXT", false),
+                ],
+            )],
+        };
+        let annotation = QueryAnnotation {
+            name: "q".to_string(),
+            cardinality: QueryCardinality::Many,
+            params: Vec::new(),
+        };
+
+        let sql = "SELECT id FROM users WHERE email = :email\n  AND id > ?";
+        let ast = SQLParser::parse_sql(&SQLiteDialect {}, sql).unwrap();
+        match process_sql_statement(&ast[0], annotation.clone(), &schema) {
+            Err(QueryError::MixedPlaceholders { placeholders }) => {
+                let positions: Vec<(u64, u64, &str)> = placeholders
+                    .iter()
+                    .map(|(location, placeholder)| {
+                        (location.line, location.column, placeholder.as_str())
+                    })
+                    .collect();
+                assert_eq!(positions, vec![(1, 36, ":email"), (2, 12, "?")]);
+            }
+            other => panic!("expected mixed placeholders, got {:?}", other.map(|_| ())),
+        }
+
+        let sql = "SELECT id FROM users WHERE id = $1";
+        let ast = SQLParser::parse_sql(&MySqlDialect {}, sql).unwrap();
+        assert!(process_sql_statement(&ast[0], annotation, &schema).is_ok());
+        assert!(check_dialect_placeholders(&ast[0], &SQLDialect::SQLite).is_ok());
+        match check_dialect_placeholders(&ast[0], &SQLDialect::MySQL) {
+            Err(QueryError::UnsupportedPlaceholder {
+                placeholder,
+                location,
+                dialect,
+            }) => {
+                assert_eq!(placeholder, "$1");
+                assert_eq!((location.line, location.column), (1, 33));
+                assert_eq!(dialect, "MySQL");
+            }
+            other => panic!("expected an unsupported placeholder, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_process_sql_statement_slice_directives() {
+        use sqlparser::dialect::SQLiteDialect;
+        use sqlparser::parser::Parser as SQLParser;
+
+        let schema = SchemaParseResult {
+            tables: vec![table(
+                "orders",
+                vec![
+                    column("order_id", "INTEGER", false),
+                    column("status", "TEXT", false),
+                ],
+            )],
+        };
+        let process = |sql: &str, directives: &str| {
+            let ast = SQLParser::parse_sql(&SQLiteDialect {}, sql).unwrap();
+            let annotation =
+                extract_query_annotations(&format!("-- q :many\n{}\n{}", directives, sql))
+                    .remove(0)
+                    .unwrap();
+            process_sql_statement(&ast[0], annotation, &schema)
+        };
+
+        let sql = "SELECT order_id FROM orders WHERE order_id IN (:ids) AND status NOT IN (:skip)";
+        let mut result = process(sql, "-- @slice ids\n-- @slice skip").unwrap();
+        let fields: Vec<(&str, &str, Option<SliceBinding>)> = result
+            .input_fields
+            .iter()
+            .map(|f| (f.name.as_str(), f.data_type.as_str(), f.slice))
+            .collect();
+        assert_eq!(
+            fields,
+            vec![
+                (
+                    "ids",
+                    "int",
+                    Some(SliceBinding::Expanded { negated: false })
+                ),
+                (
+                    "skip",
+                    "str",
+                    Some(SliceBinding::Expanded { negated: true })
+                ),
+            ]
+        );
+
+        bind_slices_as_arrays(&mut result).unwrap();
+        assert_eq!(
+            result.statement.to_string(),
+            "SELECT order_id FROM orders WHERE order_id = ANY(:ids) AND status <> ALL(:skip)"
+        );
+        assert!(result
+            .input_fields
+            .iter()
+            .all(|f| f.slice == Some(SliceBinding::Array)));
+
+        match process(
+            "SELECT order_id FROM orders WHERE order_id = :ids",
+            "-- @slice ids",
+        ) {
+            Err(QueryError::InvalidParamDirective { reason, .. }) => assert_eq!(
+                reason,
+                "a @slice parameter can only be used as an item of an IN (...) list"
+            ),
+            other => panic!("expected an invalid directive, got {:?}", other.map(|_| ())),
+        }
+
+        let mut mixed = process(
+            "SELECT order_id FROM orders WHERE order_id IN (1, :ids)",
+            "-- @slice ids",
+        )
+        .unwrap();
+        assert!(matches!(
+            bind_slices_as_arrays(&mut mixed),
+            Err(QueryError::InvalidParamDirective { .. })
+        ));
+
+        let error = |sql: &str| match process(sql, "-- @slice ids") {
+            Err(QueryError::InvalidParamDirective { reason, .. }) => reason,
+            other => panic!("expected an invalid directive, got {:?}", other.map(|_| ())),
+        };
+        assert_eq!(
+            error("SELECT order_id FROM orders WHERE order_id NOT IN (1, :ids)"),
+            "a @slice parameter must be the only item of a NOT IN (...) list"
+        );
+        assert_eq!(
+            error("SELECT order_id FROM orders WHERE order_id IN (:ids) OR order_id NOT IN (:ids)"),
+            "a @slice parameter can't be used in both IN (...) and NOT IN (...) lists"
+        );
+    }
 }
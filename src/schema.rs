@@ -1,7 +1,11 @@
-use sqlparser::ast::Statement;
+use crate::query::analyze_query_output;
+use sqlparser::ast::{
+    AlterColumnOperation, AlterTableOperation, ColumnDef, ColumnOption, CreateTable, DataType,
+    Expr, IndexColumn, ObjectName, ObjectType, Query, RenameTableNameKind, Statement,
+    TableConstraint, ViewColumnDef,
+};
 use sqlparser::dialect::Dialect;
 use sqlparser::parser::{Parser as SQLParser, ParserError};
-use std::collections::HashMap;
 
 #[derive(Clone, Debug)]
 pub enum FieldSource {
@@ -11,40 +15,131 @@ pub enum FieldSource {
         table: String,
         column: String,
         data_type: String,
+        nullable: bool,
     },
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
+pub struct Column {
+    pub name: String,
+    /// The declared type; see `unknown_column_type` for derived columns whose
+    /// type couldn't be inferred.
+    pub data_type: String,
+    /// False when the column is declared `NOT NULL` or is part of the primary key.
+    pub nullable: bool,
+}
+
+/// A table-level constraint other than the primary key. Column-level
+/// `UNIQUE`, `REFERENCES` and `CHECK` options are recorded here too.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Constraint {
+    Unique {
+        name: Option<String>,
+        columns: Vec<String>,
+    },
+    ForeignKey {
+        name: Option<String>,
+        columns: Vec<String>,
+        foreign_table: String,
+        referred_columns: Vec<String>,
+    },
+    Check {
+        name: Option<String>,
+        expr: String,
+    },
+}
+
+/// The declared type given to view and CTE columns whose type couldn't be
+/// inferred. SQLite's `ANY` classifies as unknown in every backend.
+pub fn unknown_column_type() -> String {
+    "ANY".to_string()
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TableKind {
+    Table,
+    /// A `CREATE VIEW`; its columns are inferred from the view's query.
+    View,
+    /// A `WITH` clause relation, only visible to the query that defines it.
+    Cte,
+}
+
+#[derive(Clone, Debug)]
+pub struct Table {
+    pub name: String,
+    pub kind: TableKind,
+    /// The schema the table was declared in, e.g. `public` for `public.users`.
+    pub schema: Option<String>,
+    /// Columns in declaration order.
+    pub columns: Vec<Column>,
+    /// Primary key column names, in key order.
+    pub primary_key: Vec<String>,
+    pub constraints: Vec<Constraint>,
+}
+
+impl Table {
+    /// Look up a column by name.
+    pub fn column(&self, name: &str) -> Option<&Column> {
+        self.columns.iter().find(|column| column.name == name)
+    }
+
+    /// True when `name` refers to this table, either bare or schema-qualified.
+    pub fn is_named(&self, name: &str) -> bool {
+        if self.name == name {
+            return true;
+        }
+        match &self.schema {
+            Some(schema) => {
+                name.strip_prefix(schema.as_str())
+                    .and_then(|rest| rest.strip_prefix('.'))
+                    == Some(self.name.as_str())
+            }
+            None => false,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default)]
 pub struct SchemaParseResult {
-    pub table_fields: HashMap<String, HashMap<String, String>>,
+    /// Tables in declaration order.
+    pub tables: Vec<Table>,
 }
 
 impl SchemaParseResult {
-    /// Search all tables in the schema for a column named `name`.
-    pub fn resolve_fields_by_name(&self, name: &str) -> Vec<FieldSource> {
-        self.resolve_fields_in_tables(name, &[])
+    /// Look up a table by its bare or schema-qualified name.
+    pub fn table(&self, name: &str) -> Option<&Table> {
+        self.tables.iter().find(|table| table.is_named(name))
+    }
+
+    /// The columns of `table` in declaration order, if the table exists.
+    pub fn table_columns(&self, table: &str) -> Option<&[Column]> {
+        self.table(table).map(|table| table.columns.as_slice())
+    }
+
+    /// Look up a single column of `table` by name.
+    pub fn column(&self, table: &str, name: &str) -> Option<&Column> {
+        self.table(table)?.column(name)
     }
 
     /// Search only the specified `tables` for a column named `name`.
-    /// If `tables` is empty the full schema is searched (same as
-    /// `resolve_fields_by_name`).
+    /// If `tables` is empty the full schema is searched. Candidates are
+    /// returned in table declaration order.
     pub fn resolve_fields_in_tables(&self, name: &str, tables: &[&str]) -> Vec<FieldSource> {
         let mut result: Vec<FieldSource> = Vec::new();
 
-        for (table_name, table_fields) in &self.table_fields {
-            if !tables.is_empty() && !tables.contains(&table_name.as_str()) {
+        for table in &self.tables {
+            if !tables.is_empty() && !tables.iter().any(|t| table.is_named(t)) {
                 continue;
             }
-            for (field_name, field_data_type) in table_fields {
-                if name == field_name {
-                    result.push(FieldSource::TableSource {
-                        database: None,
-                        schema: None,
-                        table: table_name.clone(),
-                        column: field_name.clone(),
-                        data_type: field_data_type.clone(),
-                    })
-                }
+            if let Some(field) = table.column(name) {
+                result.push(FieldSource::TableSource {
+                    database: None,
+                    schema: table.schema.clone(),
+                    table: table.name.clone(),
+                    column: field.name.clone(),
+                    data_type: field.data_type.clone(),
+                    nullable: field.nullable,
+                })
             }
         }
 
@@ -52,34 +147,692 @@ impl SchemaParseResult {
     }
 }
 
-pub fn parse_schema_file(
-    schema_file_contents: &str,
-    parser_dialect: &dyn Dialect,
-) -> Result<SchemaParseResult, ParserError> {
-    match SQLParser::parse_sql(parser_dialect, schema_file_contents) {
-        Err(err) => Err(err),
-        Ok(ast) => {
-            let mut tables: HashMap<String, HashMap<String, String>> = HashMap::new();
+#[derive(thiserror::Error, Debug)]
+pub enum SchemaError {
+    #[error("{0}")]
+    Parse(#[from] ParserError),
+    #[error("table \"{0}\" does not exist")]
+    UnknownTable(String),
+    #[error("column \"{column}\" does not exist in table \"{table}\"")]
+    UnknownColumn { table: String, column: String },
+    #[error("column \"{column}\" already exists in table \"{table}\"")]
+    DuplicateColumn { table: String, column: String },
+    #[error("cannot infer the columns of view \"{view}\": {message}")]
+    InvalidView { view: String, message: String },
+}
 
-            for statement in ast {
-                match statement {
-                    Statement::CreateTable(create_table) => {
-                        let table_name = create_table.name.to_string();
-                        let mut columns: HashMap<String, String> = HashMap::new();
+impl SchemaParseResult {
+    /// Parse `sql` and replay its DDL onto the schema, in statement order.
+    /// Statements other than `CREATE TABLE`, `CREATE VIEW`, `ALTER TABLE` and
+    /// `DROP TABLE`/`DROP VIEW` are ignored.
+    pub fn apply_sql(
+        &mut self,
+        sql: &str,
+        parser_dialect: &dyn Dialect,
+    ) -> Result<(), SchemaError> {
+        for statement in SQLParser::parse_sql(parser_dialect, sql)? {
+            self.apply_statement(&statement)?;
+        }
+        Ok(())
+    }
 
-                        for column in create_table.columns.iter() {
-                            columns.insert(column.name.value.clone(), column.data_type.to_string());
+    pub fn apply_statement(&mut self, statement: &Statement) -> Result<(), SchemaError> {
+        match statement {
+            Statement::CreateTable(create_table) => {
+                let table = build_table(create_table);
+                self.register(table, create_table.if_not_exists);
+            }
+            Statement::CreateView {
+                name,
+                columns,
+                query,
+                if_not_exists,
+                ..
+            } => {
+                let view = self.build_view(statement, name, columns, query)?;
+                self.register(view, *if_not_exists);
+            }
+            Statement::AlterTable {
+                name,
+                if_exists,
+                operations,
+                ..
+            } => {
+                let key = object_name_key(name);
+                let table = match self.tables.iter_mut().find(|t| t.is_named(&key)) {
+                    Some(table) => table,
+                    None if *if_exists => return Ok(()),
+                    None => return Err(SchemaError::UnknownTable(key)),
+                };
+                for operation in operations {
+                    alter_table(table, operation)?;
+                }
+            }
+            Statement::Drop {
+                object_type: object_type @ (ObjectType::Table | ObjectType::View),
+                if_exists,
+                names,
+                ..
+            } => {
+                let kind = match object_type {
+                    ObjectType::View => TableKind::View,
+                    _ => TableKind::Table,
+                };
+                for name in names {
+                    let key = object_name_key(name);
+                    match self
+                        .tables
+                        .iter()
+                        .position(|t| t.kind == kind && t.is_named(&key))
+                    {
+                        Some(index) => {
+                            self.tables.remove(index);
                         }
-
-                        tables.insert(table_name, columns);
+                        None if *if_exists => {}
+                        None => return Err(SchemaError::UnknownTable(key)),
                     }
-                    _ => {}
                 }
             }
+            _ => {}
+        }
+        Ok(())
+    }
+}
+
+impl SchemaParseResult {
+    /// Add `table`, replacing any relation of the same name in the same
+    /// schema unless `if_not_exists` is set. A CTE shadows every relation of
+    /// its name, whatever the schema.
+    pub fn register(&mut self, table: Table, if_not_exists: bool) {
+        match self.tables.iter().position(|t| {
+            t.name == table.name && (table.kind == TableKind::Cte || t.schema == table.schema)
+        }) {
+            Some(_) if if_not_exists => {}
+            Some(index) => self.tables[index] = table,
+            None => self.tables.push(table),
+        }
+    }
 
-            Ok(SchemaParseResult {
-                table_fields: tables,
+    /// Infer a view's columns by analysing its query against the relations
+    /// declared so far. An explicit column list renames the inferred columns.
+    fn build_view(
+        &self,
+        statement: &Statement,
+        name: &ObjectName,
+        column_defs: &[ViewColumnDef],
+        query: &Query,
+    ) -> Result<Table, SchemaError> {
+        let (schema, name) = split_object_name(name);
+        let invalid_view = |message: String| SchemaError::InvalidView {
+            view: name.clone(),
+            message,
+        };
+
+        let output_fields = analyze_query_output(query, self)
+            .map_err(|err| invalid_view(err.format(statement, "")))?;
+        if !column_defs.is_empty() && column_defs.len() != output_fields.len() {
+            return Err(invalid_view(format!(
+                "{} column names given for {} columns",
+                column_defs.len(),
+                output_fields.len()
+            )));
+        }
+
+        let columns = output_fields
+            .into_iter()
+            .enumerate()
+            .map(|(index, field)| {
+                let column_def = column_defs.get(index);
+                Column {
+                    name: column_def.map_or(field.name, |def| def.name.value.clone()),
+                    data_type: match column_def.and_then(|def| def.data_type.as_ref()) {
+                        Some(data_type) => data_type.to_string(),
+                        None => field.sql_type.unwrap_or_else(unknown_column_type),
+                    },
+                    nullable: field.nullable,
+                }
             })
+            .collect();
+
+        Ok(Table {
+            name,
+            kind: TableKind::View,
+            schema,
+            columns,
+            primary_key: Vec::new(),
+            constraints: Vec::new(),
+        })
+    }
+}
+
+fn build_table(create_table: &CreateTable) -> Table {
+    let (schema, name) = split_object_name(&create_table.name);
+    let mut table = Table {
+        name,
+        kind: TableKind::Table,
+        schema,
+        columns: Vec::new(),
+        primary_key: collect_primary_key_columns(&create_table.constraints),
+        constraints: create_table
+            .constraints
+            .iter()
+            .filter_map(convert_table_constraint)
+            .collect(),
+    };
+
+    for column in &create_table.columns {
+        add_column(&mut table, column);
+    }
+
+    table
+}
+
+/// Append `column` to `table`, recording any column-level constraints.
+fn add_column(table: &mut Table, column: &ColumnDef) {
+    let column_name = column.name.value.clone();
+
+    for option in &column.options {
+        match &option.option {
+            // A table can only declare one primary key, so a column-level
+            // one never overlaps a `PRIMARY KEY (...)` constraint.
+            ColumnOption::Unique {
+                is_primary: true, ..
+            } => table.primary_key.push(column_name.clone()),
+            ColumnOption::Unique { .. } => table.constraints.push(Constraint::Unique {
+                name: option.name.as_ref().map(|n| n.value.clone()),
+                columns: vec![column_name.clone()],
+            }),
+            ColumnOption::ForeignKey {
+                foreign_table,
+                referred_columns,
+                ..
+            } => table.constraints.push(Constraint::ForeignKey {
+                name: option.name.as_ref().map(|n| n.value.clone()),
+                columns: vec![column_name.clone()],
+                foreign_table: foreign_table.to_string(),
+                referred_columns: referred_columns.iter().map(|c| c.value.clone()).collect(),
+            }),
+            ColumnOption::Check(expr) => table.constraints.push(Constraint::Check {
+                name: option.name.as_ref().map(|n| n.value.clone()),
+                expr: expr.to_string(),
+            }),
+            _ => {}
+        }
+    }
+
+    let options: Vec<&ColumnOption> = column.options.iter().map(|o| &o.option).collect();
+    table.columns.push(Column {
+        nullable: !table.primary_key.contains(&column_name) && is_column_nullable(&options),
+        name: column_name,
+        data_type: column.data_type.to_string(),
+    });
+}
+
+/// Apply one `ALTER TABLE` operation. Operations that don't affect column
+/// names, types or nullability are ignored.
+fn alter_table(table: &mut Table, operation: &AlterTableOperation) -> Result<(), SchemaError> {
+    match operation {
+        AlterTableOperation::AddColumn {
+            if_not_exists,
+            column_def,
+            ..
+        } => {
+            if table.column(&column_def.name.value).is_some() {
+                if *if_not_exists {
+                    return Ok(());
+                }
+                return Err(SchemaError::DuplicateColumn {
+                    table: table.name.clone(),
+                    column: column_def.name.value.clone(),
+                });
+            }
+            add_column(table, column_def);
+        }
+        AlterTableOperation::DropColumn {
+            column_names,
+            if_exists,
+            ..
+        } => {
+            for column_name in column_names {
+                let name = &column_name.value;
+                if table.column(name).is_none() {
+                    if *if_exists {
+                        continue;
+                    }
+                    return Err(unknown_column(table, name));
+                }
+                table.columns.retain(|c| &c.name != name);
+                table.primary_key.retain(|c| c != name);
+                // Dropping a column drops the constraints that cover it.
+                table.constraints.retain(|constraint| match constraint {
+                    Constraint::Unique { columns, .. } | Constraint::ForeignKey { columns, .. } => {
+                        !columns.contains(name)
+                    }
+                    Constraint::Check { .. } => true,
+                });
+            }
         }
+        AlterTableOperation::RenameColumn {
+            old_column_name,
+            new_column_name,
+        } => rename_column(table, &old_column_name.value, &new_column_name.value)?,
+        AlterTableOperation::RenameTable { table_name } => {
+            let (RenameTableNameKind::As(new_name) | RenameTableNameKind::To(new_name)) =
+                table_name;
+            let (schema, name) = split_object_name(new_name);
+            table.name = name;
+            if schema.is_some() {
+                table.schema = schema;
+            }
+        }
+        AlterTableOperation::AlterColumn { column_name, op } => {
+            let in_primary_key = table.primary_key.contains(&column_name.value);
+            let column = match table
+                .columns
+                .iter_mut()
+                .find(|c| c.name == column_name.value)
+            {
+                Some(column) => column,
+                None => return Err(unknown_column(table, &column_name.value)),
+            };
+            match op {
+                AlterColumnOperation::SetNotNull => column.nullable = false,
+                AlterColumnOperation::DropNotNull => column.nullable = !in_primary_key,
+                AlterColumnOperation::SetDataType { data_type, .. } => {
+                    column.data_type = data_type.to_string()
+                }
+                _ => {}
+            }
+        }
+        // MySQL `CHANGE COLUMN` and `MODIFY COLUMN` redefine the column in place.
+        AlterTableOperation::ChangeColumn {
+            old_name,
+            new_name,
+            data_type,
+            options,
+            ..
+        } => {
+            rename_column(table, &old_name.value, &new_name.value)?;
+            redefine_column(table, &new_name.value, data_type, options)?;
+        }
+        AlterTableOperation::ModifyColumn {
+            col_name,
+            data_type,
+            options,
+            ..
+        } => redefine_column(table, &col_name.value, data_type, options)?,
+        AlterTableOperation::AddConstraint { constraint, .. } => {
+            let primary_key = collect_primary_key_columns(std::slice::from_ref(constraint));
+            if !primary_key.is_empty() {
+                for column in table.columns.iter_mut() {
+                    if primary_key.contains(&column.name) {
+                        column.nullable = false;
+                    }
+                }
+                table.primary_key = primary_key;
+            }
+            table
+                .constraints
+                .extend(convert_table_constraint(constraint));
+        }
+        AlterTableOperation::DropConstraint { name, .. } => {
+            table.constraints.retain(|constraint| {
+                let constraint_name = match constraint {
+                    Constraint::Unique { name, .. }
+                    | Constraint::ForeignKey { name, .. }
+                    | Constraint::Check { name, .. } => name,
+                };
+                constraint_name.as_deref() != Some(name.value.as_str())
+            });
+        }
+        AlterTableOperation::DropPrimaryKey { .. } => table.primary_key.clear(),
+        _ => {}
+    }
+    Ok(())
+}
+
+fn rename_column(table: &mut Table, old_name: &str, new_name: &str) -> Result<(), SchemaError> {
+    if old_name != new_name && table.column(new_name).is_some() {
+        return Err(SchemaError::DuplicateColumn {
+            table: table.name.clone(),
+            column: new_name.to_string(),
+        });
+    }
+    let column = match table.columns.iter_mut().find(|c| c.name == old_name) {
+        Some(column) => column,
+        None => return Err(unknown_column(table, old_name)),
+    };
+    column.name = new_name.to_string();
+
+    let rename = |name: &mut String| {
+        if name == old_name {
+            *name = new_name.to_string();
+        }
+    };
+    table.primary_key.iter_mut().for_each(rename);
+    for constraint in table.constraints.iter_mut() {
+        if let Constraint::Unique { columns, .. } | Constraint::ForeignKey { columns, .. } =
+            constraint
+        {
+            columns.iter_mut().for_each(rename);
+        }
+    }
+    Ok(())
+}
+
+fn redefine_column(
+    table: &mut Table,
+    name: &str,
+    data_type: &DataType,
+    options: &[ColumnOption],
+) -> Result<(), SchemaError> {
+    let in_primary_key = table.primary_key.iter().any(|c| c == name);
+    let column = match table.columns.iter_mut().find(|c| c.name == name) {
+        Some(column) => column,
+        None => return Err(unknown_column(table, name)),
+    };
+    let options: Vec<&ColumnOption> = options.iter().collect();
+    column.data_type = data_type.to_string();
+    column.nullable = !in_primary_key && is_column_nullable(&options);
+    Ok(())
+}
+
+fn unknown_column(table: &Table, column: &str) -> SchemaError {
+    SchemaError::UnknownColumn {
+        table: table.name.clone(),
+        column: column.to_string(),
+    }
+}
+
+/// The name a statement uses for a table, as matched by `Table::is_named`.
+/// Quoted identifiers are unquoted.
+pub fn object_name_key(name: &ObjectName) -> String {
+    match split_object_name(name) {
+        (Some(schema), table) => format!("{}.{}", schema, table),
+        (None, table) => table,
+    }
+}
+
+/// Split `schema.table` into its parts. Any database qualifier is dropped.
+fn split_object_name(name: &ObjectName) -> (Option<String>, String) {
+    let parts: Vec<String> = name
+        .0
+        .iter()
+        .map(|part| match part.as_ident() {
+            Some(ident) => ident.value.clone(),
+            None => part.to_string(),
+        })
+        .collect();
+
+    match &parts[..] {
+        [.., schema, table] => (Some(schema.clone()), table.clone()),
+        _ => (None, parts.concat()),
+    }
+}
+
+fn convert_table_constraint(constraint: &TableConstraint) -> Option<Constraint> {
+    match constraint {
+        TableConstraint::Unique { name, columns, .. } => Some(Constraint::Unique {
+            name: name.as_ref().map(|n| n.value.clone()),
+            columns: columns.iter().filter_map(index_column_name).collect(),
+        }),
+        TableConstraint::ForeignKey {
+            name,
+            columns,
+            foreign_table,
+            referred_columns,
+            ..
+        } => Some(Constraint::ForeignKey {
+            name: name.as_ref().map(|n| n.value.clone()),
+            columns: columns.iter().map(|c| c.value.clone()).collect(),
+            foreign_table: foreign_table.to_string(),
+            referred_columns: referred_columns.iter().map(|c| c.value.clone()).collect(),
+        }),
+        TableConstraint::Check { name, expr, .. } => Some(Constraint::Check {
+            name: name.as_ref().map(|n| n.value.clone()),
+            expr: expr.to_string(),
+        }),
+        _ => None,
+    }
+}
+
+fn index_column_name(index_column: &IndexColumn) -> Option<String> {
+    match &index_column.column.expr {
+        Expr::Identifier(ident) => Some(ident.value.clone()),
+        _ => None,
+    }
+}
+
+/// A column is nullable unless it carries a `NOT NULL` or `PRIMARY KEY` option.
+fn is_column_nullable(options: &[&ColumnOption]) -> bool {
+    !options.iter().any(|option| {
+        matches!(
+            option,
+            ColumnOption::NotNull
+                | ColumnOption::Unique {
+                    is_primary: true,
+                    ..
+                }
+        )
+    })
+}
+
+/// Collect the column names named by table-level `PRIMARY KEY (...)` constraints.
+fn collect_primary_key_columns(constraints: &[TableConstraint]) -> Vec<String> {
+    let mut result: Vec<String> = Vec::new();
+
+    for constraint in constraints {
+        if let TableConstraint::PrimaryKey { columns, .. } = constraint {
+            result.extend(columns.iter().filter_map(index_column_name));
+        }
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sqlparser::dialect::PostgreSqlDialect;
+
+    #[test]
+    fn test_parse_schema_file_table_model() {
+        let schema_sql = "
+            CREATE TABLE public.users (
+                id INTEGER PRIMARY KEY,
+                email TEXT NOT NULL UNIQUE,
+                bio TEXT
+            );
+            CREATE TABLE order_items (
+                order_id INTEGER REFERENCES orders (id),
+                product_id INTEGER,
+                quantity INTEGER CHECK (quantity > 0),
+                PRIMARY KEY (order_id, product_id)
+            );
+        ";
+        let mut schema = SchemaParseResult::default();
+        schema.apply_sql(schema_sql, &PostgreSqlDialect {}).unwrap();
+
+        let names: Vec<&str> = schema.tables.iter().map(|t| t.name.as_str()).collect();
+        assert_eq!(names, vec!["users", "order_items"]);
+
+        let users = schema.table("public.users").unwrap();
+        assert!(schema.table("users").is_some());
+        assert_eq!(users.schema.as_deref(), Some("public"));
+        let columns: Vec<(&str, bool)> = users
+            .columns
+            .iter()
+            .map(|c| (c.name.as_str(), c.nullable))
+            .collect();
+        assert_eq!(
+            columns,
+            vec![("id", false), ("email", false), ("bio", true)]
+        );
+        assert_eq!(users.primary_key, vec!["id"]);
+        assert_eq!(
+            users.constraints,
+            vec![Constraint::Unique {
+                name: None,
+                columns: vec!["email".to_string()]
+            }]
+        );
+
+        let order_items = schema.table("order_items").unwrap();
+        assert_eq!(order_items.primary_key, vec!["order_id", "product_id"]);
+        assert!(!order_items.column("product_id").unwrap().nullable);
+        assert_eq!(
+            order_items.constraints,
+            vec![
+                Constraint::ForeignKey {
+                    name: None,
+                    columns: vec!["order_id".to_string()],
+                    foreign_table: "orders".to_string(),
+                    referred_columns: vec!["id".to_string()],
+                },
+                Constraint::Check {
+                    name: None,
+                    expr: "quantity > 0".to_string(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_apply_sql_migrations() {
+        let migrations = [
+            "CREATE TABLE users (id INTEGER PRIMARY KEY, email TEXT NOT NULL);
+             CREATE TABLE sessions (id INTEGER PRIMARY KEY);",
+            "ALTER TABLE users ADD COLUMN nickname TEXT;
+             ALTER TABLE users ADD COLUMN legacy INTEGER UNIQUE;
+             ALTER TABLE users RENAME COLUMN email TO email_address;",
+            "ALTER TABLE users DROP COLUMN legacy;
+             ALTER TABLE users ALTER COLUMN nickname SET NOT NULL;
+             ALTER TABLE users RENAME TO accounts;
+             DROP TABLE sessions;",
+        ];
+        let mut schema = SchemaParseResult::default();
+        for sql in migrations {
+            schema.apply_sql(sql, &PostgreSqlDialect {}).unwrap();
+        }
+
+        let names: Vec<&str> = schema.tables.iter().map(|t| t.name.as_str()).collect();
+        assert_eq!(names, vec!["accounts"]);
+
+        let accounts = schema.table("accounts").unwrap();
+        let columns: Vec<(&str, bool)> = accounts
+            .columns
+            .iter()
+            .map(|c| (c.name.as_str(), c.nullable))
+            .collect();
+        assert_eq!(
+            columns,
+            vec![("id", false), ("email_address", false), ("nickname", false)]
+        );
+        assert!(accounts.constraints.is_empty());
+
+        let err = schema
+            .apply_sql("ALTER TABLE users ADD COLUMN x TEXT", &PostgreSqlDialect {})
+            .unwrap_err();
+        assert!(matches!(err, SchemaError::UnknownTable(name) if name == "users"));
+        let err = schema
+            .apply_sql("ALTER TABLE accounts DROP COLUMN x", &PostgreSqlDialect {})
+            .unwrap_err();
+        assert!(matches!(err, SchemaError::UnknownColumn { .. }));
+        schema
+            .apply_sql(
+                "DROP TABLE IF EXISTS users; ALTER TABLE accounts DROP COLUMN IF EXISTS x;",
+                &PostgreSqlDialect {},
+            )
+            .unwrap();
+    }
+
+    #[test]
+    fn test_tables_in_different_schemas() {
+        let mut schema = SchemaParseResult::default();
+        schema
+            .apply_sql(
+                "CREATE TABLE public.users (id INTEGER PRIMARY KEY, email TEXT NOT NULL);
+                 CREATE TABLE audit.users (id INTEGER PRIMARY KEY, changed_at TIMESTAMP);
+                 CREATE TABLE IF NOT EXISTS audit.users (id INTEGER);",
+                &PostgreSqlDialect {},
+            )
+            .unwrap();
+
+        let tables: Vec<(Option<&str>, &str, usize)> = schema
+            .tables
+            .iter()
+            .map(|t| (t.schema.as_deref(), t.name.as_str(), t.columns.len()))
+            .collect();
+        assert_eq!(
+            tables,
+            vec![(Some("public"), "users", 2), (Some("audit"), "users", 2)]
+        );
+        assert!(schema.column("public.users", "email").is_some());
+        assert!(schema.column("audit.users", "changed_at").is_some());
+    }
+
+    #[test]
+    fn test_alter_column_drop_not_null() {
+        let mut schema = SchemaParseResult::default();
+        schema
+            .apply_sql(
+                "CREATE TABLE users (id INTEGER PRIMARY KEY, email TEXT NOT NULL);
+                 ALTER TABLE users ALTER COLUMN email DROP NOT NULL;
+                 ALTER TABLE users ALTER COLUMN id DROP NOT NULL;",
+                &PostgreSqlDialect {},
+            )
+            .unwrap();
+
+        let columns: Vec<(&str, bool)> = schema
+            .table("users")
+            .unwrap()
+            .columns
+            .iter()
+            .map(|c| (c.name.as_str(), c.nullable))
+            .collect();
+        // A primary key column stays NOT NULL.
+        assert_eq!(columns, vec![("id", false), ("email", true)]);
+    }
+
+    #[test]
+    fn test_create_view_infers_columns() {
+        let schema_sql = "
+            CREATE TABLE users (id INTEGER PRIMARY KEY, email TEXT NOT NULL, bio TEXT);
+            CREATE TABLE orders (order_id INTEGER PRIMARY KEY, user_id INTEGER NOT NULL);
+            CREATE VIEW user_orders AS
+                SELECT u.id, u.email, o.order_id
+                FROM users u LEFT JOIN orders o ON o.user_id = u.id;
+            CREATE VIEW emails (address) AS SELECT email FROM user_orders;
+        ";
+        let mut schema = SchemaParseResult::default();
+        schema.apply_sql(schema_sql, &PostgreSqlDialect {}).unwrap();
+
+        let view = schema.table("user_orders").unwrap();
+        assert_eq!(view.kind, TableKind::View);
+        let columns: Vec<(&str, &str, bool)> = view
+            .columns
+            .iter()
+            .map(|c| (c.name.as_str(), c.data_type.as_str(), c.nullable))
+            .collect();
+        assert_eq!(
+            columns,
+            vec![
+                ("id", "INTEGER", false),
+                ("email", "TEXT", false),
+                ("order_id", "INTEGER", true)
+            ]
+        );
+
+        let emails = schema.table("emails").unwrap();
+        assert_eq!(emails.columns[0].name, "address");
+        assert_eq!(emails.columns[0].data_type, "TEXT");
+
+        let err = SchemaParseResult::default()
+            .apply_sql(
+                "CREATE VIEW v AS SELECT x FROM nowhere",
+                &PostgreSqlDialect {},
+            )
+            .unwrap_err();
+        assert!(matches!(err, SchemaError::InvalidView { view, .. } if view == "v"));
     }
 }
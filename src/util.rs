@@ -31,10 +31,7 @@ pub fn extract_line_number_from_parse_error(parse_error: &str) -> i32 {
         None => return -1,
     };
 
-    match before_comma.parse::<i32>() {
-        Ok(n) => n,
-        Err(_) => -1,
-    }
+    before_comma.parse::<i32>().unwrap_or(-1)
 }
 
 pub fn format_sql_parser_error(error: &ParserError, sql: &str) -> String {